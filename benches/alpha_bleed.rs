@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use tarmac::alpha_bleed::{alpha_bleed, alpha_bleed_rgba, BleedChannels};
+
+const SIZE: u32 = 2048;
+
+/// A spritesheet-like image: a grid of opaque squares with transparent gaps
+/// between them, which is what alpha bleeding spends most of its time on.
+fn spritesheet() -> RgbaImage {
+    RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        if x % 64 < 48 && y % 64 < 48 {
+            Rgba([(x % 256) as u8, (y % 256) as u8, 128, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    })
+}
+
+fn bench_alpha_bleed(c: &mut Criterion) {
+    let image = spritesheet();
+
+    let mut group = c.benchmark_group("alpha_bleed 2048x2048");
+    group.sample_size(10);
+
+    group.bench_function("DynamicImage", |b| {
+        b.iter_batched(
+            || DynamicImage::ImageRgba8(image.clone()),
+            |mut image| {
+                alpha_bleed(&mut image, BleedChannels::all(), None);
+                image
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("raw buffer", |b| {
+        b.iter_batched(
+            || image.as_raw().clone(),
+            |mut data| {
+                alpha_bleed_rgba(&mut data, SIZE, SIZE, BleedChannels::all(), None);
+                data
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_alpha_bleed);
+criterion_main!(benches);
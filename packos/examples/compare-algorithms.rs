@@ -0,0 +1,44 @@
+//! Packs the same set of sprites with every algorithm and prints how much of
+//! the resulting buckets each one fills. Edit the sizes in
+//! `shared/sample_sprites.rs` to match your own sprites to find out which
+//! algorithm suits them best.
+
+#[path = "shared/sample_sprites.rs"]
+mod sample_sprites;
+
+use packos::SimplePacker;
+
+use sample_sprites::{sample_sprites, ALGORITHMS};
+
+fn main() {
+    env_logger::init();
+
+    let inputs = sample_sprites();
+
+    for &algorithm in ALGORITHMS {
+        let result = SimplePacker::new()
+            .max_size((512, 512))
+            .padding(1)
+            .algorithm(algorithm)
+            .pack(&inputs);
+
+        let mut used = 0;
+        let mut total = 0;
+
+        for bucket in result.buckets() {
+            total += bucket.size().0 * bucket.size().1;
+            used += bucket
+                .items()
+                .iter()
+                .map(|item| item.size().0 * item.size().1)
+                .sum::<u32>();
+        }
+
+        println!(
+            "{:?}: {} bucket(s), {:.1}% occupied",
+            algorithm,
+            result.buckets().len(),
+            f64::from(used) / f64::from(total) * 100.0
+        );
+    }
+}
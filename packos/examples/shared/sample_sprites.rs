@@ -0,0 +1,31 @@
+//! A sample set of sprites shared by the compare-algorithms example and the
+//! algorithm tests.
+
+use packos::{InputItem, PackAlgorithm};
+
+/// Every algorithm, in the order they're compared in.
+pub const ALGORITHMS: &[PackAlgorithm] = &[
+    PackAlgorithm::Simple,
+    PackAlgorithm::Shelf,
+    PackAlgorithm::Skyline,
+    PackAlgorithm::MaxRects,
+];
+
+/// A mix of sprite sizes like a typical UI project has: many small icons, some
+/// wide and tall bars, and a few large images.
+pub fn sample_sprites() -> Vec<InputItem> {
+    let sizes = [
+        ((32, 32), 24),
+        ((64, 64), 10),
+        ((100, 20), 10),
+        ((20, 100), 6),
+        ((48, 16), 12),
+        ((200, 150), 3),
+        ((90, 60), 8),
+    ];
+
+    sizes
+        .iter()
+        .flat_map(|&(size, count)| (0..count).map(move |_| InputItem::new(size)))
+        .collect()
+}
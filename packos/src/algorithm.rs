@@ -0,0 +1,380 @@
+use crate::geometry::Rect;
+
+/// The strategy a packer uses to decide where each item goes in a bucket.
+///
+/// Items are always placed largest first. Which algorithm packs tightest
+/// depends on the sizes of the items being packed, so it's worth comparing
+/// them on real inputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PackAlgorithm {
+    /// Places each item at the first corner of an already placed item where it
+    /// fits. This is the algorithm Packos has always used.
+    #[default]
+    Simple,
+
+    /// Places items left to right in rows as tall as the first item in each
+    /// row. Fast and predictable, and works well when items have similar
+    /// heights.
+    Shelf,
+
+    /// Tracks the top edge of everything placed so far, and places each item
+    /// where it ends up lowest.
+    Skyline,
+
+    /// Tracks every free rectangle in the bucket, and places each item in the
+    /// one that it fits most snugly. Usually the tightest, but the slowest.
+    MaxRects,
+}
+
+/// Decides where items go in a single bucket.
+pub(crate) trait Placer {
+    /// Finds a position where an item of the given size fits, without placing
+    /// it there.
+    fn find(&self, size: (u32, u32)) -> Option<(u32, u32)>;
+
+    /// Records that the given space is taken.
+    fn place(&mut self, rect: Rect);
+}
+
+pub(crate) fn placer(algorithm: PackAlgorithm, bucket_size: (u32, u32)) -> Box<dyn Placer> {
+    match algorithm {
+        PackAlgorithm::Simple => Box::new(AnchorPlacer::new(bucket_size)),
+        PackAlgorithm::Shelf => Box::new(ShelfPlacer::new(bucket_size)),
+        PackAlgorithm::Skyline => Box::new(SkylinePlacer::new(bucket_size)),
+        PackAlgorithm::MaxRects => Box::new(MaxRectsPlacer::new(bucket_size)),
+    }
+}
+
+struct AnchorPlacer {
+    bucket_size: (u32, u32),
+    anchors: Vec<(u32, u32)>,
+    placed: Vec<Rect>,
+}
+
+impl AnchorPlacer {
+    fn new(bucket_size: (u32, u32)) -> Self {
+        Self {
+            bucket_size,
+            anchors: vec![(0, 0)],
+            placed: Vec::new(),
+        }
+    }
+
+    fn fits_at(&self, anchor: (u32, u32), size: (u32, u32)) -> bool {
+        let potential_rect = Rect { pos: anchor, size };
+
+        let fits_with_others = self
+            .placed
+            .iter()
+            .all(|placed| !potential_rect.intersects(placed));
+
+        let max = potential_rect.max();
+        let fits_in_bucket = max.0 < self.bucket_size.0 && max.1 < self.bucket_size.1;
+
+        fits_with_others && fits_in_bucket
+    }
+}
+
+impl Placer for AnchorPlacer {
+    fn find(&self, size: (u32, u32)) -> Option<(u32, u32)> {
+        self.anchors
+            .iter()
+            .copied()
+            .find(|&anchor| self.fits_at(anchor, size))
+    }
+
+    fn place(&mut self, rect: Rect) {
+        if let Some(index) = self.anchors.iter().position(|&anchor| anchor == rect.pos) {
+            self.anchors.remove(index);
+        }
+
+        let new_anchor_hor = (rect.pos.0 + rect.size.0, rect.pos.1);
+        if new_anchor_hor.0 < self.bucket_size.0 && new_anchor_hor.1 < self.bucket_size.1 {
+            self.anchors.push(new_anchor_hor);
+        }
+
+        let new_anchor_ver = (rect.pos.0, rect.pos.1 + rect.size.1);
+        if new_anchor_ver.0 < self.bucket_size.0 && new_anchor_ver.1 < self.bucket_size.1 {
+            self.anchors.push(new_anchor_ver);
+        }
+
+        self.placed.push(rect);
+    }
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+}
+
+struct ShelfPlacer {
+    bucket_size: (u32, u32),
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPlacer {
+    fn new(bucket_size: (u32, u32)) -> Self {
+        Self {
+            bucket_size,
+            shelves: Vec::new(),
+        }
+    }
+
+    fn next_shelf_y(&self) -> u32 {
+        self.shelves
+            .last()
+            .map(|shelf| shelf.y + shelf.height)
+            .unwrap_or(0)
+    }
+}
+
+impl Placer for ShelfPlacer {
+    fn find(&self, size: (u32, u32)) -> Option<(u32, u32)> {
+        let existing = self.shelves.iter().find(|shelf| {
+            size.1 <= shelf.height && shelf.used_width + size.0 <= self.bucket_size.0
+        });
+
+        if let Some(shelf) = existing {
+            return Some((shelf.used_width, shelf.y));
+        }
+
+        let y = self.next_shelf_y();
+        if size.0 <= self.bucket_size.0 && y + size.1 <= self.bucket_size.1 {
+            Some((0, y))
+        } else {
+            None
+        }
+    }
+
+    fn place(&mut self, rect: Rect) {
+        match self.shelves.iter_mut().find(|shelf| shelf.y == rect.pos.1) {
+            Some(shelf) => shelf.used_width = rect.max().0,
+            None => self.shelves.push(Shelf {
+                y: rect.pos.1,
+                height: rect.size.1,
+                used_width: rect.max().0,
+            }),
+        }
+    }
+}
+
+/// A horizontal span of the skyline, at a height of `y`.
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+struct SkylinePlacer {
+    bucket_size: (u32, u32),
+
+    /// Sorted left to right, covering the whole width of the bucket.
+    segments: Vec<SkylineSegment>,
+}
+
+impl SkylinePlacer {
+    fn new(bucket_size: (u32, u32)) -> Self {
+        Self {
+            bucket_size,
+            segments: vec![SkylineSegment {
+                x: 0,
+                y: 0,
+                width: bucket_size.0,
+            }],
+        }
+    }
+
+    /// The height an item of the given width would rest at if its left edge
+    /// was at the start of the given segment.
+    fn resting_height(&self, start: usize, width: u32) -> Option<u32> {
+        let x = self.segments[start].x;
+        if x + width > self.bucket_size.0 {
+            return None;
+        }
+
+        let y = self.segments[start..]
+            .iter()
+            .take_while(|segment| segment.x < x + width)
+            .map(|segment| segment.y)
+            .max()
+            .unwrap_or(0);
+
+        Some(y)
+    }
+}
+
+impl Placer for SkylinePlacer {
+    fn find(&self, size: (u32, u32)) -> Option<(u32, u32)> {
+        (0..self.segments.len())
+            .filter_map(|index| {
+                let y = self.resting_height(index, size.0)?;
+                let x = self.segments[index].x;
+
+                if y + size.1 <= self.bucket_size.1 {
+                    Some((x, y))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|&(x, y)| (y, x))
+    }
+
+    fn place(&mut self, rect: Rect) {
+        let (left, right) = (rect.pos.0, rect.max().0);
+        let top = SkylineSegment {
+            x: left,
+            y: rect.max().1,
+            width: rect.size.0,
+        };
+
+        let mut segments = Vec::with_capacity(self.segments.len() + 2);
+        let mut top = Some(top);
+
+        for segment in self.segments.drain(..) {
+            let end = segment.x + segment.width;
+
+            if end <= left || segment.x >= right {
+                if segment.x >= right {
+                    segments.extend(top.take());
+                }
+
+                segments.push(segment);
+                continue;
+            }
+
+            // Keep the parts of this segment that stick out from under the
+            // item.
+            if segment.x < left {
+                segments.push(SkylineSegment {
+                    x: segment.x,
+                    y: segment.y,
+                    width: left - segment.x,
+                });
+            }
+
+            segments.extend(top.take());
+
+            if end > right {
+                segments.push(SkylineSegment {
+                    x: right,
+                    y: segment.y,
+                    width: end - right,
+                });
+            }
+        }
+
+        segments.extend(top);
+
+        // Merge neighboring segments at the same height.
+        let mut merged: Vec<SkylineSegment> = Vec::with_capacity(segments.len());
+        for segment in segments {
+            match merged.last_mut() {
+                Some(last) if last.y == segment.y => last.width += segment.width,
+                _ => merged.push(segment),
+            }
+        }
+
+        self.segments = merged;
+    }
+}
+
+struct MaxRectsPlacer {
+    free: Vec<Rect>,
+}
+
+impl MaxRectsPlacer {
+    fn new(bucket_size: (u32, u32)) -> Self {
+        Self {
+            free: vec![Rect {
+                pos: (0, 0),
+                size: bucket_size,
+            }],
+        }
+    }
+}
+
+impl Placer for MaxRectsPlacer {
+    fn find(&self, size: (u32, u32)) -> Option<(u32, u32)> {
+        // Best short side fit: prefer free rectangles that leave the least
+        // space along one side of the item.
+        self.free
+            .iter()
+            .filter(|free| size.0 <= free.size.0 && size.1 <= free.size.1)
+            .min_by_key(|free| {
+                let leftover = (free.size.0 - size.0, free.size.1 - size.1);
+                (
+                    leftover.0.min(leftover.1),
+                    leftover.0.max(leftover.1),
+                    free.pos.1,
+                    free.pos.0,
+                )
+            })
+            .map(|free| free.pos)
+    }
+
+    fn place(&mut self, rect: Rect) {
+        let mut free = Vec::with_capacity(self.free.len() + 4);
+
+        for existing in self.free.drain(..) {
+            if !existing.intersects(&rect) {
+                free.push(existing);
+                continue;
+            }
+
+            // Split the free rectangle into the up to four maximal rectangles
+            // around the item.
+            let existing_max = existing.max();
+            let rect_max = rect.max();
+
+            if rect.pos.0 > existing.pos.0 {
+                free.push(Rect {
+                    pos: existing.pos,
+                    size: (rect.pos.0 - existing.pos.0, existing.size.1),
+                });
+            }
+
+            if rect_max.0 < existing_max.0 {
+                free.push(Rect {
+                    pos: (rect_max.0, existing.pos.1),
+                    size: (existing_max.0 - rect_max.0, existing.size.1),
+                });
+            }
+
+            if rect.pos.1 > existing.pos.1 {
+                free.push(Rect {
+                    pos: existing.pos,
+                    size: (existing.size.0, rect.pos.1 - existing.pos.1),
+                });
+            }
+
+            if rect_max.1 < existing_max.1 {
+                free.push(Rect {
+                    pos: (existing.pos.0, rect_max.1),
+                    size: (existing.size.0, existing_max.1 - rect_max.1),
+                });
+            }
+        }
+
+        // Drop free rectangles that are entirely inside of another one, since
+        // they can never be a better fit. Of identical rectangles, only the
+        // first is kept.
+        let mut index = 0;
+        while index < free.len() {
+            let current = free[index];
+            let contained = free.iter().enumerate().any(|(other, rect)| {
+                other != index
+                    && rect.contains(&current)
+                    && (other < index || !current.contains(rect))
+            });
+
+            if contained {
+                free.swap_remove(index);
+            } else {
+                index += 1;
+            }
+        }
+
+        self.free = free;
+    }
+}
@@ -15,6 +15,17 @@ impl Rect {
         x_intersect && y_intersect
     }
 
+    /// Whether the other rectangle is entirely inside of this one.
+    pub fn contains(&self, other: &Rect) -> bool {
+        let self_max = self.max();
+        let other_max = other.max();
+
+        self.pos.0 <= other.pos.0
+            && self.pos.1 <= other.pos.1
+            && other_max.0 <= self_max.0
+            && other_max.1 <= self_max.1
+    }
+
     pub fn max(&self) -> (u32, u32) {
         (self.pos.0 + self.size.0, self.pos.1 + self.size.1)
     }
@@ -2,9 +2,9 @@
 //! [Tarmac](https://github.com/Roblox/tarmac), a tool that manages assets for
 //! Roblox projects, including packing images into spritesheets.
 //!
-//! Packos currently exposes a single packing implementation,
-//! [`SimplePacker`][SimplePacker]. More algorithms can be added in the future
-//! using the same basic types that Packos uses.
+//! Packos exposes a single packer, [`SimplePacker`][SimplePacker], which can
+//! place items using any of the algorithms in
+//! [`PackAlgorithm`][PackAlgorithm].
 //!
 //! ## Example
 //! ```
@@ -28,12 +28,15 @@
 //! ```
 //!
 //! [SimplePacker]: struct.SimplePacker.html
+//! [PackAlgorithm]: enum.PackAlgorithm.html
 
+mod algorithm;
 mod geometry;
 mod id;
 mod packer;
 mod types;
 
+pub use algorithm::PackAlgorithm;
 pub use id::*;
 pub use packer::*;
 pub use types::*;
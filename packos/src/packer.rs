@@ -1,16 +1,21 @@
 use std::{borrow::Borrow, cmp::Reverse};
 
 use crate::{
+    algorithm::{placer, PackAlgorithm},
     geometry::Rect,
     types::{Bucket, InputItem, OutputItem, PackOutput},
 };
 
-/// A configurable rectangle packer using a simple packing algorithm.
+/// A configurable rectangle packer. By default, it uses a simple packing
+/// algorithm, but others can be picked with `SimplePacker::algorithm`.
 #[derive(Debug, Clone)]
 pub struct SimplePacker {
     min_size: (u32, u32),
     max_size: (u32, u32),
     padding: u32,
+    symmetric_padding: bool,
+    allow_rotation: bool,
+    algorithm: PackAlgorithm,
 }
 
 impl Default for SimplePacker {
@@ -24,11 +29,17 @@ impl SimplePacker {
     /// * `min_size` of 128x128
     /// * `max_size` of 1024x1024
     /// * `padding` of 0
+    /// * `symmetric_padding` of false
+    /// * `allow_rotation` of false
+    /// * `algorithm` of `PackAlgorithm::Simple`
     pub fn new() -> Self {
         Self {
             min_size: (128, 128),
             max_size: (1024, 1024),
             padding: 0,
+            symmetric_padding: false,
+            allow_rotation: false,
+            algorithm: PackAlgorithm::default(),
         }
     }
 
@@ -40,10 +51,36 @@ impl SimplePacker {
         Self { max_size, ..self }
     }
 
+    /// Sets the amount of empty space to leave after each item, to its right
+    /// and below it.
     pub fn padding(self, padding: u32) -> Self {
         Self { padding, ..self }
     }
 
+    /// Leaves padding on every side of each item instead, so that items never
+    /// touch each other or the edges of their bucket.
+    pub fn symmetric_padding(self, symmetric_padding: bool) -> Self {
+        Self {
+            symmetric_padding,
+            ..self
+        }
+    }
+
+    /// Allows the packer to rotate items by 90 degrees when they don't fit in
+    /// their original orientation. Rotated items are marked with
+    /// `OutputItem::rotated`, and their size is reported as it was placed.
+    pub fn allow_rotation(self, allow_rotation: bool) -> Self {
+        Self {
+            allow_rotation,
+            ..self
+        }
+    }
+
+    /// Sets the algorithm used to decide where each item goes.
+    pub fn algorithm(self, algorithm: PackAlgorithm) -> Self {
+        Self { algorithm, ..self }
+    }
+
     /// Pack a group of input rectangles into zero or more buckets.
     ///
     /// Accepts any type that can turn into an iterator of anything that can
@@ -58,8 +95,14 @@ impl SimplePacker {
         let mut remaining_items: Vec<_> = items.into_iter().map(|item| *item.borrow()).collect();
         remaining_items.sort_by_key(|input| Reverse(input.area()));
 
+        let (leading_padding, total_padding) = if self.symmetric_padding {
+            (self.padding, self.padding * 2)
+        } else {
+            (0, self.padding)
+        };
+
         for item in &mut remaining_items {
-            item.size = (item.size.0 + self.padding, item.size.1 + self.padding);
+            item.size = (item.size.0 + total_padding, item.size.1 + total_padding);
         }
 
         let num_items = remaining_items.len();
@@ -73,8 +116,7 @@ impl SimplePacker {
             let mut current_size = self.min_size;
 
             loop {
-                let (bucket, next_remaining) =
-                    Self::pack_one_bucket(&remaining_items, current_size);
+                let (bucket, next_remaining) = self.pack_one_bucket(&remaining_items, current_size);
 
                 // If this size was large enough to contain the rest of the
                 // images, we're done packing!
@@ -104,9 +146,13 @@ impl SimplePacker {
 
         for bucket in &mut buckets {
             for item in &mut bucket.items {
+                item.rect.pos = (
+                    item.rect.pos.0 + leading_padding,
+                    item.rect.pos.1 + leading_padding,
+                );
                 item.rect.size = (
-                    item.rect.size.0 - self.padding,
-                    item.rect.size.1 - self.padding,
+                    item.rect.size.0 - total_padding,
+                    item.rect.size.1 - total_padding,
                 );
             }
         }
@@ -121,6 +167,7 @@ impl SimplePacker {
     }
 
     fn pack_one_bucket(
+        &self,
         remaining_items: &[InputItem],
         bucket_size: (u32, u32),
     ) -> (Bucket, Vec<InputItem>) {
@@ -130,58 +177,51 @@ impl SimplePacker {
             bucket_size
         );
 
-        let mut anchors = vec![(0, 0)];
+        let mut placer = placer(self.algorithm, bucket_size);
         let mut items: Vec<OutputItem> = Vec::new();
         let mut unpacked_items = Vec::new();
 
         for input_item in remaining_items {
             log::trace!(
-                "For item {:?} ({}x{}), evaluating these anchors: {:?}",
+                "Placing item {:?} ({}x{})",
                 input_item.id(),
                 input_item.size.0,
                 input_item.size.1,
-                anchors
             );
 
-            let fit_anchor = anchors.iter().copied().position(|anchor| {
-                let potential_rect = Rect {
-                    pos: anchor,
-                    size: input_item.size,
+            // Prefer placing items in their original orientation, only trying a
+            // rotated placement if the item doesn't fit as-is.
+            let rotated_size = (input_item.size.1, input_item.size.0);
+            let try_rotation = self.allow_rotation && rotated_size != input_item.size;
+
+            let fit = placer
+                .find(input_item.size)
+                .map(|pos| (pos, false))
+                .or_else(|| {
+                    if try_rotation {
+                        placer.find(rotated_size).map(|pos| (pos, true))
+                    } else {
+                        None
+                    }
+                });
+
+            if let Some((pos, rotated)) = fit {
+                let size = if rotated {
+                    rotated_size
+                } else {
+                    input_item.size
                 };
 
-                let fits_with_others = items
-                    .iter()
-                    .all(|packed_item| !potential_rect.intersects(&packed_item.rect));
-
-                let max = potential_rect.max();
-                let fits_in_bucket = max.0 < bucket_size.0 && max.1 < bucket_size.1;
-
-                fits_with_others && fits_in_bucket
-            });
-
-            if let Some(index) = fit_anchor {
-                let anchor = anchors.remove(index);
-
-                log::trace!("Fit at anchor {:?}", anchor);
+                log::trace!("Fit at {:?} (rotated: {})", pos, rotated);
 
-                let new_anchor_hor = (anchor.0 + input_item.size.0, anchor.1);
-                if new_anchor_hor.0 < bucket_size.0 && new_anchor_hor.1 < bucket_size.1 {
-                    anchors.push(new_anchor_hor);
-                }
-
-                let new_anchor_ver = (anchor.0, anchor.1 + input_item.size.1);
-                if new_anchor_ver.0 < bucket_size.0 && new_anchor_ver.1 < bucket_size.1 {
-                    anchors.push(new_anchor_ver);
-                }
+                let rect = Rect { pos, size };
+                placer.place(rect);
 
-                let output_item = OutputItem {
+                items.push(OutputItem {
                     id: input_item.id(),
-                    rect: Rect {
-                        pos: anchor,
-                        size: input_item.size,
-                    },
-                };
-                items.push(output_item);
+                    rect,
+                    rotated,
+                });
             } else {
                 log::trace!("Did not fit in this bucket.");
 
@@ -197,3 +237,101 @@ impl SimplePacker {
         (bucket, unpacked_items)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn total_area(output: &PackOutput) -> u32 {
+        output
+            .buckets()
+            .iter()
+            .map(|bucket| bucket.size().0 * bucket.size().1)
+            .sum()
+    }
+
+    fn narrow_sprites() -> Vec<InputItem> {
+        vec![
+            InputItem::new((120, 30)),
+            InputItem::new((120, 30)),
+            InputItem::new((30, 120)),
+            InputItem::new((30, 120)),
+        ]
+    }
+
+    #[test]
+    fn rotation_packs_narrow_sprites_tighter() {
+        let packer = SimplePacker::new()
+            .min_size((128, 128))
+            .max_size((128, 128));
+
+        let unrotated = packer.clone().pack(narrow_sprites());
+        let rotated = packer.allow_rotation(true).pack(narrow_sprites());
+
+        assert_eq!(unrotated.buckets().len(), 2);
+        assert_eq!(rotated.buckets().len(), 1);
+        assert!(total_area(&rotated) < total_area(&unrotated));
+
+        let rotated_items: Vec<_> = rotated.buckets()[0]
+            .items()
+            .iter()
+            .filter(|item| item.rotated())
+            .collect();
+
+        assert_eq!(rotated_items.len(), 2);
+        for item in rotated_items {
+            assert_eq!(item.size(), (120, 30));
+        }
+    }
+
+    #[test]
+    fn padding_follows_items_by_default() {
+        let output = SimplePacker::new()
+            .padding(2)
+            .pack(vec![InputItem::new((10, 10)), InputItem::new((10, 10))]);
+
+        let items = output.buckets()[0].items();
+        assert_eq!(items[0].position(), (0, 0));
+        assert_eq!(items[0].size(), (10, 10));
+        assert_eq!(items[1].position(), (12, 0));
+        assert_eq!(items[1].size(), (10, 10));
+    }
+
+    #[test]
+    fn padding_can_be_symmetric() {
+        let output = SimplePacker::new()
+            .padding(2)
+            .symmetric_padding(true)
+            .pack(vec![InputItem::new((10, 10)), InputItem::new((10, 10))]);
+
+        let items = output.buckets()[0].items();
+        assert_eq!(items[0].position(), (2, 2));
+        assert_eq!(items[0].size(), (10, 10));
+
+        // Each item gets its own gutter, so neighbors are two paddings apart.
+        assert_eq!(items[1].position(), (16, 2));
+        assert_eq!(items[1].size(), (10, 10));
+    }
+
+    #[test]
+    fn occupancy_ignores_padding() {
+        let output = SimplePacker::new()
+            .min_size((32, 32))
+            .padding(2)
+            .pack(vec![InputItem::new((10, 10)), InputItem::new((10, 10))]);
+
+        let bucket = &output.buckets()[0];
+        assert_eq!(bucket.size(), (32, 32));
+        assert_eq!(bucket.used_area(), 200);
+        assert!((bucket.occupancy() - 200.0 / 1024.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn rotation_is_off_by_default() {
+        let output = SimplePacker::new().pack(narrow_sprites());
+
+        for bucket in output.buckets() {
+            assert!(bucket.items().iter().all(|item| !item.rotated()));
+        }
+    }
+}
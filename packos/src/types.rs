@@ -40,10 +40,14 @@ impl InputItem {
 /// `OutputItem` corresponds 1:1 to `InputItem` objects that were passed into
 /// the packing function. They expose the ID from the input, as well as position
 /// and size.
+///
+/// If the packer rotated an item, its size is reported as it was placed, which
+/// is the input's size with the width and height swapped.
 #[derive(Debug, Clone, Copy)]
 pub struct OutputItem {
     pub(crate) id: Id,
     pub(crate) rect: Rect,
+    pub(crate) rotated: bool,
 }
 
 impl OutputItem {
@@ -71,13 +75,21 @@ impl OutputItem {
     pub fn max(&self) -> (u32, u32) {
         self.rect.max()
     }
+
+    /// Whether this item was rotated by 90 degrees clockwise when it was
+    /// placed.
+    #[inline]
+    pub fn rotated(&self) -> bool {
+        self.rotated
+    }
 }
 
 /// The results from running a packing function.
 ///
-/// Currently only exposes the list of buckets that inputs were grouped into. In
-/// the future, this struct may also have information about inputs that didn't
-/// fit and how efficient the result is.
+/// Currently only exposes the list of buckets that inputs were grouped into.
+/// Each bucket reports how efficiently it was packed through
+/// [`Bucket::occupancy`]. In the future, this struct may also have information
+/// about inputs that didn't fit.
 #[derive(Debug, Clone)]
 pub struct PackOutput {
     pub(crate) buckets: Vec<Bucket>,
@@ -108,4 +120,25 @@ impl Bucket {
     pub fn items(&self) -> &[OutputItem] {
         &self.items
     }
+
+    /// The total area covered by the items in this bucket, not counting any
+    /// padding around them.
+    pub fn used_area(&self) -> u64 {
+        self.items
+            .iter()
+            .map(|item| u64::from(item.rect.size.0) * u64::from(item.rect.size.1))
+            .sum()
+    }
+
+    /// The fraction of this bucket's area that's covered by its items, from 0
+    /// to 1.
+    pub fn occupancy(&self) -> f32 {
+        let total_area = u64::from(self.size.0) * u64::from(self.size.1);
+
+        if total_area == 0 {
+            0.0
+        } else {
+            (self.used_area() as f64 / total_area as f64) as f32
+        }
+    }
 }
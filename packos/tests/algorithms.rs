@@ -0,0 +1,99 @@
+//! Compares the packing algorithms on the same sample sprites as the
+//! compare-algorithms example.
+
+#[path = "../examples/shared/sample_sprites.rs"]
+mod sample_sprites;
+
+use packos::{PackOutput, SimplePacker};
+
+use sample_sprites::{sample_sprites, ALGORITHMS};
+
+/// The fraction of the area of every bucket that's covered by items.
+fn occupancy(output: &PackOutput) -> f64 {
+    let mut used = 0;
+    let mut total = 0;
+
+    for bucket in output.buckets() {
+        used += bucket.used_area();
+        total += u64::from(bucket.size().0) * u64::from(bucket.size().1);
+    }
+
+    used as f64 / total as f64
+}
+
+#[test]
+fn every_algorithm_packs_valid_slices() {
+    let inputs = sample_sprites();
+
+    for &algorithm in ALGORITHMS {
+        let output = SimplePacker::new()
+            .max_size((512, 512))
+            .padding(1)
+            .algorithm(algorithm)
+            .pack(&inputs);
+
+        let mut packed = 0;
+
+        for bucket in output.buckets() {
+            let items = bucket.items();
+            packed += items.len();
+
+            for (i, item) in items.iter().enumerate() {
+                let max = item.max();
+                assert!(max.0 <= bucket.size().0 && max.1 <= bucket.size().1);
+
+                for other in &items[i + 1..] {
+                    let overlaps = item.position().0 < other.max().0
+                        && other.position().0 < item.max().0
+                        && item.position().1 < other.max().1
+                        && other.position().1 < item.max().1;
+
+                    assert!(!overlaps, "{:?} placed overlapping items", algorithm);
+                }
+            }
+        }
+
+        assert_eq!(packed, inputs.len(), "{:?} lost items", algorithm);
+    }
+}
+
+#[test]
+fn algorithms_place_items_differently() {
+    let inputs = sample_sprites();
+
+    let outputs: Vec<_> = ALGORITHMS
+        .iter()
+        .map(|&algorithm| {
+            SimplePacker::new()
+                .max_size((512, 512))
+                .algorithm(algorithm)
+                .pack(&inputs)
+        })
+        .collect();
+
+    let layouts: Vec<Vec<_>> = outputs
+        .iter()
+        .map(|output| {
+            output
+                .buckets()
+                .iter()
+                .flat_map(|bucket| bucket.items())
+                .map(|item| (item.id(), item.position()))
+                .collect()
+        })
+        .collect();
+
+    for (i, layout) in layouts.iter().enumerate() {
+        for (j, other) in layouts.iter().enumerate().skip(i + 1) {
+            assert_ne!(
+                layout, other,
+                "{:?} and {:?} packed the same way",
+                ALGORITHMS[i], ALGORITHMS[j]
+            );
+        }
+    }
+
+    // The sample sprites vary a lot in size, which MaxRects handles better
+    // than the default algorithm.
+    assert!(occupancy(&outputs[3]) > occupancy(&outputs[0]));
+}
@@ -2,12 +2,222 @@
 //! their nearest non-transparent neighbor. This fixes artifacting when images
 //! are resized in some contexts.
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt};
 
-use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+use image::DynamicImage;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
-pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
-    let (w, h) = img.dimensions();
+/// The set of color channels that alpha bleeding is allowed to write to.
+///
+/// Channels that are not included keep whatever value they had in the source
+/// image. This is useful for images that store data instead of color, like
+/// normal maps, where bleeding would corrupt the encoded values.
+///
+/// Serialized as a string of channel letters, like `"rgb"` or `"rb"`. An empty
+/// string disables bleeding entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BleedChannels {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+}
+
+impl BleedChannels {
+    pub fn all() -> Self {
+        Self {
+            red: true,
+            green: true,
+            blue: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            red: false,
+            green: false,
+            blue: false,
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        !self.red && !self.green && !self.blue
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut channels = Self::none();
+
+        for channel in value.chars() {
+            match channel.to_ascii_lowercase() {
+                'r' => channels.red = true,
+                'g' => channels.green = true,
+                'b' => channels.blue = true,
+                _ => {
+                    return Err(format!(
+                        "Invalid alpha bleed channel '{}'. Valid channels are r, g, and b.",
+                        channel
+                    ))
+                }
+            }
+        }
+
+        Ok(channels)
+    }
+}
+
+impl Default for BleedChannels {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl fmt::Display for BleedChannels {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.red {
+            write!(formatter, "r")?;
+        }
+        if self.green {
+            write!(formatter, "g")?;
+        }
+        if self.blue {
+            write!(formatter, "b")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for BleedChannels {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BleedChannels {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        BleedChannels::parse(&value).map_err(D::Error::custom)
+    }
+}
+
+/// Bleeds color into transparent pixels of the image.
+///
+/// If `distance` is given, only transparent pixels at most that many pixels
+/// away from a non-transparent pixel are changed. Otherwise, every transparent
+/// pixel reachable from a non-transparent one is changed.
+///
+/// RGBA images are bled in place. Other images with an alpha channel are
+/// converted to RGBA first, keeping 16 bits per channel if they had them.
+/// Images without an alpha channel are left alone.
+pub fn alpha_bleed(img: &mut DynamicImage, channels: BleedChannels, distance: Option<u32>) {
+    if channels.is_none() || distance == Some(0) {
+        return;
+    }
+
+    match img {
+        DynamicImage::ImageRgba8(buffer) => {
+            let (w, h) = buffer.dimensions();
+            alpha_bleed_rgba(buffer, w, h, channels, distance);
+        }
+        DynamicImage::ImageRgba16(buffer) => {
+            let (w, h) = buffer.dimensions();
+            bleed_pixels(buffer, w, h, channels, distance);
+        }
+        _ if !img.color().has_alpha() => {}
+        DynamicImage::ImageLumaA16(_) => {
+            let mut buffer = img.to_rgba16();
+            let (w, h) = buffer.dimensions();
+            bleed_pixels(&mut buffer, w, h, channels, distance);
+            *img = DynamicImage::ImageRgba16(buffer);
+        }
+        _ => {
+            let mut buffer = img.to_rgba8();
+            let (w, h) = buffer.dimensions();
+            alpha_bleed_rgba(&mut buffer, w, h, channels, distance);
+            *img = DynamicImage::ImageRgba8(buffer);
+        }
+    }
+}
+
+/// Bleeds color into transparent pixels of a raw RGBA8 buffer with the given
+/// dimensions, like [`alpha_bleed`].
+///
+/// # Panics
+///
+/// Panics if `data` isn't exactly `width * height * 4` bytes long.
+pub fn alpha_bleed_rgba(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    channels: BleedChannels,
+    distance: Option<u32>,
+) {
+    assert_eq!(
+        data.len(),
+        width as usize * height as usize * 4,
+        "buffer size doesn't match a {}x{} RGBA image",
+        width,
+        height
+    );
+
+    bleed_pixels(data, width, height, channels, distance);
+}
+
+/// A channel of an RGBA pixel that can be bled, either 8 or 16 bits deep.
+trait Channel: Copy {
+    fn is_zero(self) -> bool;
+    fn widen(self) -> u32;
+
+    /// Narrows an average of channel values back down to a channel.
+    fn narrow(value: u32) -> Self;
+}
+
+impl Channel for u8 {
+    fn is_zero(self) -> bool {
+        self == 0
+    }
+
+    fn widen(self) -> u32 {
+        self.into()
+    }
+
+    fn narrow(value: u32) -> Self {
+        value as u8
+    }
+}
+
+impl Channel for u16 {
+    fn is_zero(self) -> bool {
+        self == 0
+    }
+
+    fn widen(self) -> u32 {
+        self.into()
+    }
+
+    fn narrow(value: u32) -> Self {
+        value as u16
+    }
+}
+
+/// Bleeds color into transparent pixels of an RGBA buffer, whose size has
+/// already been checked.
+fn bleed_pixels<T: Channel>(
+    data: &mut [T],
+    width: u32,
+    height: u32,
+    channels: BleedChannels,
+    distance: Option<u32>,
+) {
+    // If no channels can be touched, this image is treated as pure data and
+    // left exactly as it is.
+    if channels.is_none() || distance == Some(0) {
+        return;
+    }
+
+    let (w, h) = (width, height);
+    let offset = |x: u32, y: u32| (x as usize + y as usize * w as usize) * 4;
+    let alpha = |data: &[T], x: u32, y: u32| data[offset(x, y) + 3];
 
     // Tells whether a given position has been touched by the bleeding algorithm
     // yet and is safe to sample colors from. In the first pass, we'll set all
@@ -19,7 +229,8 @@ pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
     // traversed again.
     let mut visited = Mask2::new(w, h);
 
-    // A queue of pixels to blend with surrounding pixels with next.
+    // A queue of pixels to blend with surrounding pixels with next, along with
+    // how far away they are from the nearest opaque pixel.
     //
     // Populated initially with all pixels that border opaque pixels. We'll use
     // it to blend outwards from each opaque pixel breadth-first.
@@ -43,9 +254,7 @@ pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
     // are valid to sample from.
     for y in 0..h {
         for x in 0..w {
-            let pixel = img.get_pixel(x, y);
-
-            if pixel[3] != 0 {
+            if !alpha(data, x, y).is_zero() {
                 // This pixel is not totally transparent, so we don't need to
                 // modify it. We'll add it to the `can_be_sampled` set to
                 // indicate it's okay to sample from this pixel.
@@ -55,49 +264,59 @@ pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
             }
 
             // Check if any adjacent pixels have non-zero alpha.
-            let borders_opaque = adjacent_positions(x, y).any(|(x_source, y_source)| {
-                let source = img.get_pixel(x_source, y_source);
-                source[3] != 0
-            });
+            let borders_opaque = adjacent_positions(x, y)
+                .any(|(x_source, y_source)| !alpha(data, x_source, y_source).is_zero());
 
             if borders_opaque {
                 // This pixel is totally transparent, but borders at least one
                 // opaque pixel. We'll add it to the initial set of positions to
                 // visit.
                 visited.set(x, y);
-                to_visit.push_back((x, y));
+                to_visit.push_back((x, y, 1));
             }
         }
     }
 
-    while let Some((x, y)) = to_visit.pop_front() {
+    while let Some((x, y, depth)) = to_visit.pop_front() {
+        // Neighbors that haven't been reached yet are one pixel further away
+        // from any opaque pixel than this one.
+        let can_queue = match distance {
+            Some(distance) => depth < distance,
+            None => true,
+        };
+
         // Compute the average color from all surrounding pixels that are
         // eligible to be sampled from.
-        let mut new_color = (0, 0, 0);
+        let mut new_color = (0u32, 0u32, 0u32);
         let mut contributing = 0;
 
         for (x_source, y_source) in adjacent_positions(x, y) {
             if can_be_sampled.get(x_source, y_source) {
-                let source = img.get_pixel(x_source, y_source);
+                let source = &data[offset(x_source, y_source)..][..3];
 
                 contributing += 1;
-                new_color.0 += source[0] as u16;
-                new_color.1 += source[1] as u16;
-                new_color.2 += source[2] as u16;
-            } else if !visited.get(x_source, y_source) {
+                new_color.0 += source[0].widen();
+                new_color.1 += source[1].widen();
+                new_color.2 += source[2].widen();
+            } else if can_queue && !visited.get(x_source, y_source) {
                 visited.set(x_source, y_source);
-                to_visit.push_back((x_source, y_source));
+                to_visit.push_back((x_source, y_source, depth + 1));
             }
         }
 
-        let pixel = Rgba([
-            (new_color.0 / contributing) as u8,
-            (new_color.1 / contributing) as u8,
-            (new_color.2 / contributing) as u8,
-            0,
-        ]);
-
-        img.put_pixel(x, y, pixel);
+        // Channels that we aren't allowed to bleed into keep their original
+        // value from the source image. Bled pixels stay fully transparent.
+        let pixel = &mut data[offset(x, y)..][..4];
+        if channels.red {
+            pixel[0] = T::narrow(new_color.0 / contributing);
+        }
+        if channels.green {
+            pixel[1] = T::narrow(new_color.1 / contributing);
+        }
+        if channels.blue {
+            pixel[2] = T::narrow(new_color.2 / contributing);
+        }
+        pixel[3] = T::narrow(0);
 
         // Now that we've bled this pixel, it's eligible to be sampled from for
         // future iterations.
@@ -141,3 +360,105 @@ impl Mask2 {
         self.data[index as usize] = true;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::{GenericImageView, ImageBuffer, LumaA, Rgba, RgbaImage};
+
+    fn data_image() -> DynamicImage {
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+        img.put_pixel(1, 0, Rgba([1, 2, 3, 0]));
+
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn bleeds_all_channels_by_default() {
+        let mut img = data_image();
+        alpha_bleed(&mut img, BleedChannels::default(), None);
+
+        assert_eq!(img.get_pixel(1, 0), Rgba([10, 20, 30, 0]));
+    }
+
+    #[test]
+    fn data_image_is_preserved_without_channels() {
+        let mut img = data_image();
+        alpha_bleed(&mut img, BleedChannels::none(), None);
+
+        assert_eq!(img.get_pixel(0, 0), Rgba([10, 20, 30, 255]));
+        assert_eq!(img.get_pixel(1, 0), Rgba([1, 2, 3, 0]));
+    }
+
+    #[test]
+    fn only_selected_channels_are_bled() {
+        let mut img = data_image();
+        alpha_bleed(&mut img, BleedChannels::parse("r").unwrap(), None);
+
+        assert_eq!(img.get_pixel(1, 0), Rgba([10, 2, 3, 0]));
+    }
+
+    #[test]
+    fn bleeding_stops_at_distance() {
+        let mut img = RgbaImage::new(4, 1);
+        img.put_pixel(0, 0, Rgba([10, 20, 30, 255]));
+
+        let mut img = DynamicImage::ImageRgba8(img);
+        alpha_bleed(&mut img, BleedChannels::default(), Some(2));
+
+        assert_eq!(img.get_pixel(1, 0), Rgba([10, 20, 30, 0]));
+        assert_eq!(img.get_pixel(2, 0), Rgba([10, 20, 30, 0]));
+        assert_eq!(img.get_pixel(3, 0), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn raw_buffers_are_bled_in_place() {
+        let mut data = vec![10, 20, 30, 255, 1, 2, 3, 0];
+        alpha_bleed_rgba(&mut data, 2, 1, BleedChannels::default(), None);
+
+        assert_eq!(data, [10, 20, 30, 255, 10, 20, 30, 0]);
+    }
+
+    #[test]
+    fn other_formats_are_converted() {
+        let mut img = DynamicImage::new_luma_a8(2, 1);
+        img.as_mut_luma_alpha8()
+            .unwrap()
+            .put_pixel(0, 0, LumaA([40, 255]));
+
+        alpha_bleed(&mut img, BleedChannels::default(), None);
+
+        assert_eq!(img.get_pixel(1, 0), Rgba([40, 40, 40, 0]));
+    }
+
+    #[test]
+    fn sixteen_bit_images_keep_their_depth() {
+        let mut img = ImageBuffer::new(2, 1);
+        img.put_pixel(0, 0, Rgba([1000u16, 2000, 3000, 65535]));
+
+        let mut img = DynamicImage::ImageRgba16(img);
+        alpha_bleed(&mut img, BleedChannels::default(), None);
+
+        let buffer = img.as_rgba16().unwrap();
+        assert_eq!(buffer.get_pixel(1, 0), &Rgba([1000, 2000, 3000, 0]));
+
+        let mut img = DynamicImage::new_luma_a16(2, 1);
+        img.as_mut_luma_alpha16()
+            .unwrap()
+            .put_pixel(0, 0, LumaA([1000, 65535]));
+
+        alpha_bleed(&mut img, BleedChannels::default(), None);
+
+        let buffer = img.as_rgba16().unwrap();
+        assert_eq!(buffer.get_pixel(1, 0), &Rgba([1000, 1000, 1000, 0]));
+    }
+
+    #[test]
+    fn parse_channels() {
+        assert_eq!(BleedChannels::parse("rgb").unwrap(), BleedChannels::all());
+        assert_eq!(BleedChannels::parse("").unwrap(), BleedChannels::none());
+        assert!(BleedChannels::parse("rga").is_err());
+    }
+}
@@ -34,11 +34,6 @@ impl AssetName {
 
         AssetName(displayed.into())
     }
-
-    #[cfg(test)]
-    pub(crate) fn new<S: AsRef<str>>(inner: S) -> Self {
-        Self(inner.as_ref().into())
-    }
 }
 
 impl AsRef<str> for AssetName {
@@ -0,0 +1,234 @@
+//! Exports the layout of packed spritesheets as a sprite atlas descriptor in
+//! the JSON format used by TexturePacker's multi-atlas export.
+//!
+//! This lets sprite libraries that aren't specific to Roblox consume Tarmac's
+//! spritesheets directly.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use fs_err::File;
+use serde::Serialize;
+
+use crate::{
+    asset_name::AssetName,
+    data::{AssetId, ImageSlice},
+};
+
+#[derive(Debug, Serialize)]
+pub struct AtlasDescriptor {
+    pub textures: Vec<AtlasTexture>,
+    pub meta: AtlasMeta,
+}
+
+/// A single spritesheet and the frames packed into it.
+#[derive(Debug, Serialize)]
+pub struct AtlasTexture {
+    /// The URL of the uploaded spritesheet.
+    pub image: String,
+    pub format: &'static str,
+    pub scale: u32,
+    pub frames: Vec<AtlasFrame>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtlasFrame {
+    pub filename: String,
+
+    /// The region of the spritesheet containing this frame. Following
+    /// TexturePacker, the width and height are those of the frame before it
+    /// was rotated.
+    pub frame: Rect,
+
+    /// Whether the frame was rotated by 90 degrees clockwise when packed.
+    pub rotated: bool,
+    pub trimmed: bool,
+    pub sprite_source_size: Rect,
+    pub source_size: Size,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AtlasMeta {
+    pub app: &'static str,
+    pub version: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Size {
+    pub w: u32,
+    pub h: u32,
+}
+
+impl AtlasDescriptor {
+    /// Build a descriptor from every packed input, grouping them by the
+    /// spritesheet they were uploaded as part of.
+    pub fn new<'a, I>(packed_inputs: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a AssetName, &'a AssetId, ImageSlice)>,
+    {
+        let mut frames_by_sheet: BTreeMap<&AssetId, BTreeMap<&AssetName, ImageSlice>> =
+            BTreeMap::new();
+
+        for (name, id, slice) in packed_inputs {
+            frames_by_sheet.entry(id).or_default().insert(name, slice);
+        }
+
+        let textures = frames_by_sheet
+            .into_iter()
+            .map(|(id, frames)| AtlasTexture {
+                image: id.to_string(),
+                format: "RGBA8888",
+                scale: 1,
+                frames: frames
+                    .into_iter()
+                    .map(|(name, slice)| AtlasFrame::new(name, slice))
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            textures,
+            meta: AtlasMeta {
+                app: env!("CARGO_PKG_HOMEPAGE"),
+                version: env!("CARGO_PKG_VERSION"),
+            },
+        }
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs_err::create_dir_all(parent)?;
+        }
+
+        let mut file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.flush()
+    }
+}
+
+impl AtlasFrame {
+    fn new(name: &AssetName, slice: ImageSlice) -> Self {
+        let (x, y) = slice.min();
+        let (placed_w, placed_h) = slice.size();
+
+        let (w, h) = if slice.rotated() {
+            (placed_h, placed_w)
+        } else {
+            (placed_w, placed_h)
+        };
+
+        let (sprite_source_size, source_size) = match slice.trim() {
+            Some(trim) => (
+                Rect {
+                    x: trim.offset.0,
+                    y: trim.offset.1,
+                    w,
+                    h,
+                },
+                Size {
+                    w: trim.original_size.0,
+                    h: trim.original_size.1,
+                },
+            ),
+            None => (Rect { x: 0, y: 0, w, h }, Size { w, h }),
+        };
+
+        Self {
+            filename: name.to_string(),
+            frame: Rect { x, y, w, h },
+            rotated: slice.rotated(),
+            trimmed: slice.trim().is_some(),
+            sprite_source_size,
+            source_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn sample_sheet() {
+        let root = Path::new("project");
+        let foo = AssetName::from_paths(root, &root.join("ui/foo.png"));
+        let bar = AssetName::from_paths(root, &root.join("ui/bar.png"));
+        let baz = AssetName::from_paths(root, &root.join("baz.png"));
+
+        let sheet_a = AssetId::Id(1234);
+        let sheet_b = AssetId::Id(5678);
+
+        let descriptor = AtlasDescriptor::new(vec![
+            (&foo, &sheet_a, ImageSlice::new((0, 0), (16, 32))),
+            (
+                &bar,
+                &sheet_a,
+                ImageSlice::new((17, 0), (49, 8)).with_rotated(true),
+            ),
+            (&baz, &sheet_b, ImageSlice::new((0, 0), (4, 4))),
+        ]);
+
+        let mut value = serde_json::to_value(&descriptor).unwrap();
+        value.as_object_mut().unwrap().remove("meta");
+
+        assert_eq!(
+            value,
+            json!({
+                "textures": [
+                    {
+                        "image": "rbxassetid://1234",
+                        "format": "RGBA8888",
+                        "scale": 1,
+                        "frames": [
+                            {
+                                "filename": "ui/bar.png",
+                                "frame": { "x": 17, "y": 0, "w": 8, "h": 32 },
+                                "rotated": true,
+                                "trimmed": false,
+                                "spriteSourceSize": { "x": 0, "y": 0, "w": 8, "h": 32 },
+                                "sourceSize": { "w": 8, "h": 32 },
+                            },
+                            {
+                                "filename": "ui/foo.png",
+                                "frame": { "x": 0, "y": 0, "w": 16, "h": 32 },
+                                "rotated": false,
+                                "trimmed": false,
+                                "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 32 },
+                                "sourceSize": { "w": 16, "h": 32 },
+                            },
+                        ],
+                    },
+                    {
+                        "image": "rbxassetid://5678",
+                        "format": "RGBA8888",
+                        "scale": 1,
+                        "frames": [
+                            {
+                                "filename": "baz.png",
+                                "frame": { "x": 0, "y": 0, "w": 4, "h": 4 },
+                                "rotated": false,
+                                "trimmed": false,
+                                "spriteSourceSize": { "x": 0, "y": 0, "w": 4, "h": 4 },
+                                "sourceSize": { "w": 4, "h": 4 },
+                            },
+                        ],
+                    },
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Writes files so that nothing reading them can ever see them half-written.
+
+use std::{
+    ffi::OsString,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use fs_err as fs;
+
+/// Replaces the contents of the file at `path`.
+///
+/// The contents are written to a temporary file next to it first, which is
+/// then renamed over the original. Renaming within a folder is atomic, so if
+/// Tarmac dies partway through, the file still has its old contents.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp_path = write_temp_file(path, contents)?;
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Writes the contents destined for `path` to a temporary file in the same
+/// folder and flushes them to disk, returning the temporary file's path.
+pub(crate) fn write_temp_file(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+    let temp_path = temp_path(path);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    Ok(temp_path)
+}
+
+/// The temporary file used while writing to `path`, like `foo.toml.tmp`.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(".tmp");
+
+    path.with_file_name(file_name)
+}
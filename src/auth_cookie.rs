@@ -1,29 +1,124 @@
 //! Implementation of automatically fetching authentication cookie from a Roblox
 //! Studio installation.
+//!
+//! CSRF tokens are also cached here between runs of Tarmac, so that short
+//! commands run back to back don't each spend a request fetching one. The
+//! cache file is named after a hash of the cookie, so the cookie itself is
+//! never written to disk.
 
+use std::{
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use fs_err as fs;
 use reqwest::{
     header::{self, HeaderValue},
-    Client,
+    Client, Url,
 };
 use secrecy::{ExposeSecret, SecretString};
 
-use crate::roblox_web_api::RobloxApiError;
+use crate::roblox_web_api::{execute_logged, resolve_url, RobloxApiError};
 
 pub fn get_auth_cookie() -> Option<SecretString> {
     rbx_cookie::get_value().map(SecretString::new)
 }
 
-pub fn get_csrf_token(roblosecurity_cookie: &SecretString) -> Result<HeaderValue, RobloxApiError> {
-    let response = Client::new()
-        .post("https://auth.roblox.com")
+pub fn get_csrf_token(
+    client: &Client,
+    roblosecurity_cookie: &SecretString,
+    base_url: Option<&Url>,
+) -> Result<HeaderValue, RobloxApiError> {
+    let request = client
+        .post(&resolve_url(base_url, "https://auth.roblox.com"))
         .header(header::COOKIE, roblosecurity_cookie.expose_secret())
         .header(header::CONTENT_LENGTH, 0)
-        .send();
+        .build()?;
+    let response = execute_logged(client, request)?;
 
     response
-        .unwrap()
         .headers()
         .get("X-CSRF-Token")
         .map(|v| v.to_owned())
         .ok_or(RobloxApiError::MissingCsrfToken)
 }
+
+/// Reads the CSRF token that was last used with the given cookie and API, if
+/// one was cached.
+pub fn read_cached_csrf_token(
+    roblosecurity_cookie: &SecretString,
+    base_url: Option<&Url>,
+) -> Option<HeaderValue> {
+    let path = csrf_cache_path(roblosecurity_cookie, base_url);
+    let contents = fs::read_to_string(&path).ok()?;
+
+    HeaderValue::from_str(contents.trim()).ok()
+}
+
+/// Caches a CSRF token for the given cookie and API. Failing to write the
+/// cache only costs a request next time, so errors are only logged.
+pub fn write_cached_csrf_token(
+    roblosecurity_cookie: &SecretString,
+    base_url: Option<&Url>,
+    token: &HeaderValue,
+) {
+    let path = csrf_cache_path(roblosecurity_cookie, base_url);
+
+    if let Err(err) = write_private_file(&path, token.as_bytes()) {
+        log::debug!("Couldn't cache CSRF token at {}: {}", path.display(), err);
+    }
+}
+
+/// The cache file for CSRF tokens used with the given cookie and API. Tokens
+/// are only valid for the session they were issued to, and a mock API given
+/// with --api-base-url shouldn't see Roblox's tokens.
+fn csrf_cache_path(roblosecurity_cookie: &SecretString, base_url: Option<&Url>) -> PathBuf {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(roblosecurity_cookie.expose_secret().as_bytes());
+    hasher.update(b"\0");
+    if let Some(base_url) = base_url {
+        hasher.update(base_url.as_str().as_bytes());
+    }
+
+    let hash = hasher.finalize().to_hex();
+    env::temp_dir().join(format!("tarmac-csrf-{}", &hash[..32]))
+}
+
+/// Writes a file that only the current user can read, since the temporary
+/// folder may be shared with other users.
+fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(contents)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn csrf_tokens_are_cached_by_cookie_hash() {
+        let cookie = SecretString::new(format!("cookie-{}", std::process::id()));
+        let other_cookie = SecretString::new(format!("other-{}", std::process::id()));
+        let path = csrf_cache_path(&cookie, None);
+
+        assert!(!path.to_string_lossy().contains(cookie.expose_secret()));
+        assert_ne!(path, csrf_cache_path(&other_cookie, None));
+
+        let token = HeaderValue::from_static("token");
+        write_cached_csrf_token(&cookie, None, &token);
+        assert_eq!(read_cached_csrf_token(&cookie, None), Some(token));
+        assert_eq!(read_cached_csrf_token(&other_cookie, None), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
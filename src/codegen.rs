@@ -3,30 +3,115 @@
 //! Tarmac uses a small Lua AST to build up generated code.
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     io::{self, Write},
-    path::{self, Path},
+    path::{self, Path, PathBuf},
 };
 
-use fs_err::File;
+use fs_err as fs;
+use thiserror::Error;
 
 use crate::{
     data::ImageSlice,
     data::{AssetId, SyncInput},
-    lua_ast::{Block, Expression, Function, IfBlock, Statement, Table},
+    lua_ast::{is_valid_ident, Block, Expression, Function, IfBlock, Statement, Table},
 };
 
 const CODEGEN_HEADER: &str =
     "-- This file was @generated by Tarmac. It is not intended for manual editing.";
 
-pub fn perform_codegen(output_path: Option<&Path>, inputs: &[&SyncInput]) -> io::Result<()> {
+/// Settings that apply to all generated code in a project.
+#[derive(Debug, Clone, Copy)]
+pub struct CodegenOptions<'a> {
+    /// If true, inputs with an output path get one file per top-level folder
+    /// instead of a single file.
+    pub split_root: bool,
+
+    /// The URI scheme used to refer to assets that live on the local disk
+    /// instead of on Roblox.com. See `AssetId::to_uri`.
+    pub path_scheme: &'a str,
+
+    /// If true, names in generated code that aren't valid Lua identifiers are
+    /// an error instead of a warning.
+    pub strict_names: bool,
+}
+
+/// Generates code for the given inputs. An output path of `-` writes the code
+/// to stdout instead of a file.
+pub fn perform_codegen(
+    output_path: Option<&Path>,
+    inputs: &[&SyncInput],
+    options: &CodegenOptions<'_>,
+) -> Result<(), CodegenError> {
     if let Some(path) = output_path {
-        codegen_grouped(path, inputs)
+        codegen_grouped(path, inputs, options)
     } else {
-        codegen_individual(inputs)
+        Ok(codegen_individual(inputs, options)?)
     }
 }
 
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error(
+        "Inputs {} and {} would both be named '{name}' in {}",
+        .first.display(),
+        .second.display(),
+        .output_path.display()
+    )]
+    NameConflict {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+        output_path: PathBuf,
+    },
+
+    #[error(
+        "Input {} would be named '{name}' in {}, which is also the name of a folder",
+        .path.display(),
+        .output_path.display()
+    )]
+    FolderConflict {
+        name: String,
+        path: PathBuf,
+        output_path: PathBuf,
+    },
+
+    #[error(
+        "Input {} would be named '{name}' in {}, which isn't a valid Lua identifier",
+        .path.display(),
+        .output_path.display()
+    )]
+    InvalidName {
+        name: String,
+        path: PathBuf,
+        output_path: PathBuf,
+    },
+
+    #[error("Generated code can't be split into one file per folder when it's written to stdout")]
+    SplitStdout,
+
+    #[error(
+        "'{first}' and '{second}' would both be written to {}, because some file systems ignore case",
+        .path.display()
+    )]
+    SplitFileConflict {
+        first: String,
+        second: String,
+        path: PathBuf,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+}
+
+/// Whether the given output path stands for stdout instead of a file.
+fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
 /// Tree used to track and group inputs hierarchically, before turning them into
 /// Lua tables.
 enum GroupedItem<'a> {
@@ -36,14 +121,45 @@ enum GroupedItem<'a> {
     InputGroup {
         inputs_by_dpi_scale: BTreeMap<u32, &'a SyncInput>,
     },
+    Flipbook {
+        frames: Vec<&'a SyncInput>,
+    },
+}
+
+impl GroupedItem<'_> {
+    /// The path of an input in this item, used to describe conflicts.
+    fn example_path(&self) -> Option<&Path> {
+        match self {
+            GroupedItem::Folder { .. } => None,
+            GroupedItem::InputGroup {
+                inputs_by_dpi_scale,
+            } => inputs_by_dpi_scale
+                .values()
+                .next()
+                .map(|input| &*input.path),
+            GroupedItem::Flipbook { frames } => frames.first().map(|input| &*input.path),
+        }
+    }
 }
 
 /// Perform codegen for a group of inputs who have `codegen_path` defined.
 ///
 /// We'll build up a Lua file containing nested tables that match the structure
 /// of the input's path with its base path stripped away.
-fn codegen_grouped(output_path: &Path, inputs: &[&SyncInput]) -> io::Result<()> {
+///
+/// When `options.split_root` is true, each top-level entry of that structure is
+/// instead written to its own file in a folder next to `output_path`, named
+/// after `output_path` without its extension. With `src/assets.lua`, the
+/// `icons` folder ends up in `src/assets/icons.lua`. Inputs at the top level
+/// get their own file the same way, so `logo.png` ends up in
+/// `src/assets/logo.lua`.
+fn codegen_grouped(
+    output_path: &Path,
+    inputs: &[&SyncInput],
+    options: &CodegenOptions<'_>,
+) -> Result<(), CodegenError> {
     let mut root_folder: BTreeMap<String, GroupedItem<'_>> = BTreeMap::new();
+    let mut invalid_names = BTreeSet::new();
 
     // First, collect all of the inputs and group them together into a tree
     // according to their relative paths.
@@ -80,6 +196,52 @@ fn codegen_grouped(output_path: &Path, inputs: &[&SyncInput]) -> io::Result<()>
             }
         }
 
+        // Flipbook frames are all described by one entry named after their
+        // folder.
+        if input.config.flipbook {
+            segments.pop();
+        }
+
+        // Inputs can be given a cleaner name than their file's, but they stay
+        // in the same folder.
+        if let Some(name) = &input.config.codegen_name {
+            match segments.last_mut() {
+                Some(last) => *last = name,
+                None => segments.push(name),
+            }
+        }
+
+        if segments.is_empty() {
+            log::warn!(
+                "Flipbook frame {} isn't in a folder, so it's left out of generated code. Give it a codegen-name to include it.",
+                input.path.display()
+            );
+            continue;
+        }
+
+        // Names that aren't identifiers can only be accessed with brackets,
+        // like `assets["my icon"]`, so they're worth renaming.
+        for &segment in &segments {
+            if is_valid_ident(segment) || !invalid_names.insert(segment.to_owned()) {
+                continue;
+            }
+
+            if options.strict_names {
+                return Err(CodegenError::InvalidName {
+                    name: segment.to_owned(),
+                    path: input.path.clone(),
+                    output_path: output_path.to_owned(),
+                });
+            }
+
+            log::warn!(
+                "Input {} is named '{}' in {}, which can only be accessed with brackets. Rename it or give it a codegen-name to fix this.",
+                input.path.display(),
+                segment,
+                output_path.display()
+            );
+        }
+
         // Navigate down the tree, creating any folder entries that don't exist
         // yet.
         let mut current_dir = &mut root_folder;
@@ -87,24 +249,47 @@ fn codegen_grouped(output_path: &Path, inputs: &[&SyncInput]) -> io::Result<()>
             if i == segments.len() - 1 {
                 // We assume that the last segment of a path must be a file.
 
-                let input_group = match current_dir.get_mut(segment) {
-                    Some(existing) => existing,
-                    None => {
-                        let input_group = GroupedItem::InputGroup {
+                let input_group = current_dir.entry(segment.to_owned()).or_insert_with(|| {
+                    if input.config.flipbook {
+                        GroupedItem::Flipbook { frames: Vec::new() }
+                    } else {
+                        GroupedItem::InputGroup {
                             inputs_by_dpi_scale: BTreeMap::new(),
-                        };
-                        current_dir.insert(segment.to_owned(), input_group);
-                        current_dir.get_mut(segment).unwrap()
+                        }
+                    }
+                });
+
+                match input_group {
+                    GroupedItem::Flipbook { frames } if input.config.flipbook => {
+                        frames.push(input);
+                    }
+                    GroupedItem::InputGroup {
+                        inputs_by_dpi_scale,
+                    } if !input.config.flipbook => {
+                        if let Some(existing) = inputs_by_dpi_scale.insert(input.dpi_scale, input) {
+                            return Err(CodegenError::NameConflict {
+                                name: segment.to_owned(),
+                                first: existing.path.clone(),
+                                second: input.path.clone(),
+                                output_path: output_path.to_owned(),
+                            });
+                        }
+                    }
+                    GroupedItem::Folder { .. } => {
+                        return Err(CodegenError::FolderConflict {
+                            name: segment.to_owned(),
+                            path: input.path.clone(),
+                            output_path: output_path.to_owned(),
+                        });
+                    }
+                    other => {
+                        return Err(CodegenError::NameConflict {
+                            name: segment.to_owned(),
+                            first: other.example_path().unwrap().to_owned(),
+                            second: input.path.clone(),
+                            output_path: output_path.to_owned(),
+                        });
                     }
-                };
-
-                if let GroupedItem::InputGroup {
-                    inputs_by_dpi_scale,
-                } = input_group
-                {
-                    inputs_by_dpi_scale.insert(input.dpi_scale, input);
-                } else {
-                    unreachable!();
                 }
             } else {
                 let next_entry =
@@ -114,21 +299,28 @@ fn codegen_grouped(output_path: &Path, inputs: &[&SyncInput]) -> io::Result<()>
                             children_by_name: BTreeMap::new(),
                         });
 
-                if let GroupedItem::Folder { children_by_name } = next_entry {
-                    current_dir = children_by_name;
-                } else {
-                    unreachable!();
+                match next_entry {
+                    GroupedItem::Folder { children_by_name } => current_dir = children_by_name,
+                    other => {
+                        return Err(CodegenError::FolderConflict {
+                            name: segment.to_owned(),
+                            path: other.example_path().unwrap().to_owned(),
+                            output_path: output_path.to_owned(),
+                        });
+                    }
                 }
             }
         }
     }
 
-    fn build_item(item: &GroupedItem<'_>) -> Option<Expression> {
+    fn build_item(item: &GroupedItem<'_>, options: &CodegenOptions<'_>) -> Option<Expression> {
         match item {
             GroupedItem::Folder { children_by_name } => {
                 let entries = children_by_name
                     .iter()
-                    .filter_map(|(name, child)| build_item(child).map(|item| (name.into(), item)))
+                    .filter_map(|(name, child)| {
+                        build_item(child, options).map(|item| (name.into(), item))
+                    })
                     .collect();
 
                 Some(Expression::table(entries))
@@ -136,69 +328,160 @@ fn codegen_grouped(output_path: &Path, inputs: &[&SyncInput]) -> io::Result<()>
             GroupedItem::InputGroup {
                 inputs_by_dpi_scale,
             } => {
-                if inputs_by_dpi_scale.len() == 1 {
-                    // If there is exactly one input in this group, we can
-                    // generate code knowing that there are no high DPI variants
-                    // to choose from.
+                // Variants that weren't uploaded, like ones that were too large
+                // or fully transparent, have no ID and are left out.
+                let uploaded: BTreeMap<u32, (&AssetId, &SyncInput)> = inputs_by_dpi_scale
+                    .iter()
+                    .filter_map(|(&dpi_scale, &input)| {
+                        Some((dpi_scale, (input.id.as_ref()?, input)))
+                    })
+                    .collect();
 
-                    let input = inputs_by_dpi_scale.values().next().unwrap();
+                match uploaded.len() {
+                    0 => None,
 
-                    match (&input.id, input.slice) {
-                        (Some(id), Some(slice)) => Some(codegen_url_and_slice(id, slice)),
-                        (Some(id), None) => Some(codegen_just_asset_url(id)),
-                        _ => None,
+                    // If there is exactly one uploaded input in this group, we
+                    // can generate code knowing that there are no high DPI
+                    // variants to choose from.
+                    1 => {
+                        let (id, input) = uploaded.values().next().unwrap();
+                        Some(codegen_input(id, input, options))
                     }
-                } else {
+
                     // In this case, we have the same asset in multiple
                     // different DPI scales. We can generate code to pick
                     // between them at runtime.
-                    Some(codegen_with_high_dpi_options(inputs_by_dpi_scale))
+                    _ => Some(codegen_with_high_dpi_options(&uploaded, options)),
                 }
             }
+            GroupedItem::Flipbook { frames } => codegen_flipbook(frames, options),
         }
     }
 
-    let root_item = build_item(&GroupedItem::Folder {
-        children_by_name: root_folder,
-    })
+    if options.split_root {
+        if is_stdout(output_path) {
+            return Err(CodegenError::SplitStdout);
+        }
+
+        let output_dir = output_path.with_extension("");
+        let extension = output_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("lua");
+
+        // Names are unique, but two of them could still end up as the same
+        // file where file names aren't case-sensitive. Checking before writing
+        // anything keeps one file from silently replacing another.
+        let mut names_by_file: BTreeMap<String, &str> = BTreeMap::new();
+        for name in root_folder.keys() {
+            let file_name = format!("{}.{}", name, extension);
+
+            if let Some(first) = names_by_file.insert(file_name.to_lowercase(), name) {
+                return Err(CodegenError::SplitFileConflict {
+                    first: first.to_owned(),
+                    second: name.clone(),
+                    path: output_dir.join(file_name),
+                });
+            }
+        }
+
+        fs_err::create_dir_all(&output_dir)?;
+
+        for (name, item) in &root_folder {
+            if let Some(item) = build_item(item, options) {
+                let path = output_dir.join(format!("{}.{}", name, extension));
+                write_module(&path, item)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    let root_item = build_item(
+        &GroupedItem::Folder {
+            children_by_name: root_folder,
+        },
+        options,
+    )
     .unwrap();
-    let ast = Statement::Return(root_item);
 
-    let mut file = File::create(output_path)?;
-    writeln!(file, "{}", CODEGEN_HEADER)?;
-    write!(file, "{}", ast)?;
+    if is_stdout(output_path) {
+        Ok(write_module_to_stdout(root_item)?)
+    } else {
+        Ok(write_module(output_path, root_item)?)
+    }
+}
+
+/// Prints a Lua module that returns the given expression to stdout.
+fn write_module_to_stdout(value: Expression) -> io::Result<()> {
+    let ast = Statement::Return(value);
 
-    Ok(())
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(stdout, "{}\n{}", CODEGEN_HEADER, ast)?;
+    stdout.flush()
+}
+
+/// Writes a Lua file that returns the given expression. Files that already
+/// have the same contents are left alone, so that tools watching them don't
+/// see a change.
+fn write_module(path: &Path, value: Expression) -> io::Result<()> {
+    let ast = Statement::Return(value);
+    let contents = format!("{}\n{}", CODEGEN_HEADER, ast);
+
+    if let Ok(existing) = fs::read_to_string(path) {
+        if existing == contents {
+            log::trace!("{} is unchanged", path.display());
+            return Ok(());
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs_err::create_dir_all(parent)?;
+    }
+
+    fs::write(path, contents)
 }
 
 /// Perform codegen for a group of inputs that don't have `codegen_path`
 /// defined, and so generate individual files.
-fn codegen_individual(inputs: &[&SyncInput]) -> io::Result<()> {
+fn codegen_individual(inputs: &[&SyncInput], options: &CodegenOptions<'_>) -> io::Result<()> {
     for input in inputs {
-        let expression = match (&input.id, input.slice) {
-            (Some(id), Some(slice)) => codegen_url_and_slice(id, slice),
-            (Some(id), None) => codegen_just_asset_url(id),
-            _ => continue,
+        let expression = match &input.id {
+            Some(id) => codegen_input(id, input, options),
+            None => continue,
         };
 
-        let ast = Statement::Return(expression);
-
-        let path = input.path.with_extension("lua");
-
-        let mut file = File::create(path)?;
-        writeln!(file, "{}", CODEGEN_HEADER)?;
-        write!(file, "{}", ast)?;
+        write_module(&input.path.with_extension("lua"), expression)?;
     }
 
     Ok(())
 }
 
-fn codegen_url_and_slice(id: &AssetId, slice: ImageSlice) -> Expression {
+/// Generates the value that refers to an input that has been uploaded as the
+/// given asset.
+fn codegen_input(id: &AssetId, input: &SyncInput, options: &CodegenOptions<'_>) -> Expression {
+    let url = id.to_uri(options.path_scheme);
+
+    match (input.slice, input.dimensions) {
+        (Some(slice), _) => codegen_url_and_slice(url, slice),
+
+        // Unpacked images can be described as a slice covering the whole
+        // image so that consumers can treat every image the same way.
+        (None, Some(dimensions)) if input.config.codegen_uniform_sprites => {
+            codegen_url_and_slice(url, ImageSlice::new((0, 0), dimensions))
+        }
+
+        (None, _) => Expression::String(url),
+    }
+}
+
+fn codegen_url_and_slice(url: String, slice: ImageSlice) -> Expression {
     let offset = slice.min();
     let size = slice.size();
 
     let mut table = Table::new();
-    table.add_entry("Image", id.to_string());
+    table.add_entry("Image", url);
     table.add_entry(
         "ImageRectOffset",
         Expression::Raw(format!("Vector2.new({}, {})", offset.0, offset.1)),
@@ -209,42 +492,100 @@ fn codegen_url_and_slice(id: &AssetId, slice: ImageSlice) -> Expression {
         Expression::Raw(format!("Vector2.new({}, {})", size.0, size.1)),
     );
 
+    // Rotated slices were turned 90 degrees clockwise when packed, so
+    // consumers need to know to turn them back.
+    if slice.rotated() {
+        table.add_entry("ImageRotated", Expression::Raw("true".to_owned()));
+    }
+
+    // Trimmed slices lost their transparent border, so consumers need to know
+    // where the slice goes within the image's original bounds.
+    if let Some(trim) = slice.trim() {
+        table.add_entry(
+            "TrimOffset",
+            Expression::Raw(format!("Vector2.new({}, {})", trim.offset.0, trim.offset.1)),
+        );
+        table.add_entry(
+            "OriginalSize",
+            Expression::Raw(format!(
+                "Vector2.new({}, {})",
+                trim.original_size.0, trim.original_size.1
+            )),
+        );
+    }
+
     Expression::Table(table)
 }
 
-fn codegen_just_asset_url(id: &AssetId) -> Expression {
-    Expression::String(id.to_string())
-}
+/// Describes a flipbook's spritesheet, the layout of its grid, and each of its
+/// frames in order. Flipbooks with frames that haven't been packed yet are left
+/// out.
+fn codegen_flipbook(frames: &[&SyncInput], options: &CodegenOptions<'_>) -> Option<Expression> {
+    let mut frames = frames.to_vec();
+    frames.sort_by_key(|input| (input.frame_number(), &input.name));
 
-fn codegen_dpi_option(input: &SyncInput) -> (Expression, Block) {
-    let condition = Expression::Raw(format!("dpiScale >= {}", input.dpi_scale));
+    let mut packed = Vec::with_capacity(frames.len());
+    for input in &frames {
+        packed.push((input.id.as_ref()?, input.slice?));
+    }
+
+    let (id, first_slice) = packed[0];
+    let frame_size = first_slice.size();
+    let columns: BTreeSet<_> = packed.iter().map(|(_, slice)| slice.min().0).collect();
+    let rows: BTreeSet<_> = packed.iter().map(|(_, slice)| slice.min().1).collect();
+
+    let mut frame_table = Table::new();
+    for (index, (id, slice)) in packed.iter().enumerate() {
+        frame_table.add_entry(
+            Expression::Raw(format!("[{}]", index + 1)),
+            codegen_url_and_slice(id.to_uri(options.path_scheme), *slice),
+        );
+    }
 
-    // FIXME: We should probably pull data out of SyncInput at the start of
-    // codegen so that we can handle invariants like this.
-    let id = input.id.as_ref().unwrap();
+    let mut table = Table::new();
+    table.add_entry("Image", id.to_uri(options.path_scheme));
+    table.add_entry("Columns", Expression::Raw(columns.len().to_string()));
+    table.add_entry("Rows", Expression::Raw(rows.len().to_string()));
+    table.add_entry("FrameCount", Expression::Raw(packed.len().to_string()));
+    table.add_entry(
+        "Fps",
+        Expression::Raw(frames[0].config.flipbook_fps.to_string()),
+    );
+    table.add_entry(
+        "FrameSize",
+        Expression::Raw(format!("Vector2.new({}, {})", frame_size.0, frame_size.1)),
+    );
+    table.add_entry("Frames", frame_table);
 
-    let value = match input.slice {
-        Some(slice) => codegen_url_and_slice(id, slice),
-        None => codegen_just_asset_url(id),
-    };
+    Some(Expression::Table(table))
+}
 
-    let body = Statement::Return(value);
+fn codegen_dpi_option(
+    (id, input): &(&AssetId, &SyncInput),
+    options: &CodegenOptions<'_>,
+) -> (Expression, Block) {
+    let condition = Expression::Raw(format!("dpiScale >= {}", input.dpi_scale));
+
+    let body = Statement::Return(codegen_input(id, input, options));
 
     (condition, body.into())
 }
 
-fn codegen_with_high_dpi_options(inputs: &BTreeMap<u32, &SyncInput>) -> Expression {
+fn codegen_with_high_dpi_options(
+    inputs: &BTreeMap<u32, (&AssetId, &SyncInput)>,
+    options: &CodegenOptions<'_>,
+) -> Expression {
     let args = "dpiScale".to_owned();
 
     let mut options_high_to_low = inputs.values().rev().peekable();
 
     let highest_dpi_option = options_high_to_low.next().unwrap();
-    let (highest_cond, highest_body) = codegen_dpi_option(highest_dpi_option);
+    let (highest_cond, highest_body) = codegen_dpi_option(highest_dpi_option, options);
 
     let mut if_block = IfBlock::new(highest_cond, highest_body);
 
     while let Some(dpi_option) = options_high_to_low.next() {
-        let (cond, body) = codegen_dpi_option(dpi_option);
+        let (cond, body) = codegen_dpi_option(dpi_option, options);
 
         if options_high_to_low.peek().is_some() {
             if_block.else_if_blocks.push((cond, body));
@@ -257,3 +598,273 @@ fn codegen_with_high_dpi_options(inputs: &BTreeMap<u32, &SyncInput>) -> Expressi
 
     Expression::Function(Function::new(args, statements))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+
+    use crate::{asset_name::AssetName, data::InputConfig, dpi_scale};
+
+    /// The expected output of `grouped_output_does_not_depend_on_input_order`.
+    const GROUPED_GOLDEN: &str = r#"-- This file was @generated by Tarmac. It is not intended for manual editing.
+return {
+	icons = {
+		a = function(dpiScale)
+			if dpiScale >= 2 then
+				return "rbxassetid://2"
+			else
+				return "rbxassetid://4"
+			end
+		end,
+		b = "rbxassetid://1",
+	},
+	logo = "rbxassetid://3",
+}"#;
+
+    fn input(root: &Path, relative_path: &str, id: u64) -> SyncInput {
+        let path = root.join(relative_path);
+        let path_info = dpi_scale::extract_path_info(&path);
+
+        let mut config: InputConfig =
+            toml::from_str("glob = \"**/*.png\"\ncodegen = true").unwrap();
+        config.codegen_base_path = root.to_owned();
+
+        SyncInput {
+            name: AssetName::from_paths(root, &path),
+            path,
+            path_without_dpi_scale: path_info.path_without_dpi_scale,
+            dpi_scale: path_info.dpi_scale,
+            config,
+            contents: None,
+            hash: String::new(),
+            dimensions: None,
+            id: Some(AssetId::Id(id)),
+            slice: None,
+        }
+    }
+
+    #[test]
+    fn grouped_output_does_not_depend_on_input_order() {
+        let root = env::temp_dir().join(format!("tarmac-codegen-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        let inputs = [
+            input(&root, "icons/b.png", 1),
+            input(&root, "icons/a@2x.png", 2),
+            input(&root, "logo.png", 3),
+            input(&root, "icons/a.png", 4),
+        ];
+        let options = CodegenOptions {
+            split_root: false,
+            path_scheme: "rbxasset",
+            strict_names: false,
+        };
+        let output_path = root.join("assets.lua");
+
+        let forward: Vec<_> = inputs.iter().collect();
+        perform_codegen(Some(&output_path), &forward, &options).unwrap();
+        let first = fs::read_to_string(&output_path).unwrap();
+
+        fs::remove_file(&output_path).unwrap();
+
+        let reversed: Vec<_> = inputs.iter().rev().collect();
+        perform_codegen(Some(&output_path), &reversed, &options).unwrap();
+        let second = fs::read_to_string(&output_path).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first, GROUPED_GOLDEN);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn dpi_variants_without_ids_are_left_out() {
+        let root = env::temp_dir().join(format!("tarmac-codegen-no-id-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        let mut skipped = input(&root, "icon@2x.png", 2);
+        skipped.id = None;
+        let mut unused = input(&root, "unused.png", 4);
+        unused.id = None;
+        let inputs = [
+            input(&root, "icon.png", 1),
+            skipped,
+            input(&root, "icon@3x.png", 3),
+            unused,
+        ];
+        let inputs: Vec<_> = inputs.iter().collect();
+
+        let options = CodegenOptions {
+            split_root: false,
+            path_scheme: "rbxasset",
+            strict_names: false,
+        };
+        let output_path = root.join("assets.lua");
+
+        perform_codegen(Some(&output_path), &inputs, &options).unwrap();
+        let generated = fs::read_to_string(&output_path).unwrap();
+        assert!(generated.contains("dpiScale >= 3"));
+        assert!(!generated.contains("dpiScale >= 2"));
+        assert!(generated.contains("rbxassetid://1"));
+        assert!(!generated.contains("unused"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn codegen_name_replaces_file_name() {
+        let root = env::temp_dir().join(format!("tarmac-codegen-name-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        let mut renamed = input(&root, "icons/ic_close_24px.png", 1);
+        renamed.config.codegen_name = Some("close".to_owned());
+        let inputs = [renamed, input(&root, "icons/open.png", 2)];
+        let inputs: Vec<_> = inputs.iter().collect();
+
+        let options = CodegenOptions {
+            split_root: false,
+            path_scheme: "rbxasset",
+            strict_names: false,
+        };
+        let output_path = root.join("assets.lua");
+
+        perform_codegen(Some(&output_path), &inputs, &options).unwrap();
+        let generated = fs::read_to_string(&output_path).unwrap();
+        assert!(generated.contains("close = \"rbxassetid://1\""));
+        assert!(!generated.contains("ic_close_24px"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn conflicting_codegen_names_are_an_error() {
+        let root = Path::new("project");
+
+        let mut renamed = input(root, "icons/ic_close_24px.png", 1);
+        renamed.config.codegen_name = Some("close".to_owned());
+        let inputs = [renamed, input(root, "icons/close.png", 2)];
+        let inputs: Vec<_> = inputs.iter().collect();
+
+        let options = CodegenOptions {
+            split_root: false,
+            path_scheme: "rbxasset",
+            strict_names: false,
+        };
+
+        match perform_codegen(Some(&root.join("assets.lua")), &inputs, &options) {
+            Err(CodegenError::NameConflict {
+                name,
+                first,
+                second,
+                ..
+            }) => {
+                assert_eq!(name, "close");
+                assert_eq!(first, root.join("icons/ic_close_24px.png"));
+                assert_eq!(second, root.join("icons/close.png"));
+            }
+            other => panic!("expected a name conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn strict_names_reject_names_that_need_brackets() {
+        let root = Path::new("project");
+        let inputs = [
+            input(root, "icons/close.png", 1),
+            input(root, "my-icons/open.png", 2),
+        ];
+        let inputs: Vec<_> = inputs.iter().collect();
+
+        let options = CodegenOptions {
+            split_root: false,
+            path_scheme: "rbxasset",
+            strict_names: true,
+        };
+
+        match perform_codegen(Some(&root.join("assets.lua")), &inputs, &options) {
+            Err(CodegenError::InvalidName { name, path, .. }) => {
+                assert_eq!(name, "my-icons");
+                assert_eq!(path, root.join("my-icons/open.png"));
+            }
+            other => panic!("expected an invalid name, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn split_root_writes_a_file_per_entry() {
+        let root = env::temp_dir().join(format!("tarmac-codegen-split-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        let inputs = [
+            input(&root, "icons/close.png", 1),
+            input(&root, "ui.v2/button.png", 2),
+            input(&root, "logo.png", 3),
+        ];
+        let inputs: Vec<_> = inputs.iter().collect();
+
+        let options = CodegenOptions {
+            split_root: true,
+            path_scheme: "rbxasset",
+            strict_names: false,
+        };
+
+        perform_codegen(Some(&root.join("assets.luau")), &inputs, &options).unwrap();
+
+        let output_dir = root.join("assets");
+        let icons = fs::read_to_string(output_dir.join("icons.luau")).unwrap();
+        assert!(icons.contains("close = \"rbxassetid://1\""));
+
+        // Dots in names aren't mistaken for extensions.
+        assert!(output_dir.join("ui.v2.luau").is_file());
+
+        // Top-level inputs get a file that returns just that input.
+        let logo = fs::read_to_string(output_dir.join("logo.luau")).unwrap();
+        assert!(logo.contains("return \"rbxassetid://3\""));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn split_files_differing_in_case_are_an_error() {
+        let root = Path::new("project");
+        let inputs = [
+            input(root, "Icons/close.png", 1),
+            input(root, "icons/open.png", 2),
+        ];
+        let inputs: Vec<_> = inputs.iter().collect();
+
+        let options = CodegenOptions {
+            split_root: true,
+            path_scheme: "rbxasset",
+            strict_names: false,
+        };
+
+        match perform_codegen(Some(&root.join("assets.lua")), &inputs, &options) {
+            Err(CodegenError::SplitFileConflict { first, second, .. }) => {
+                assert_eq!(first, "Icons");
+                assert_eq!(second, "icons");
+            }
+            other => panic!("expected a file conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stdout_output_cannot_be_split() {
+        let root = Path::new("project");
+        let inputs = [input(root, "icons/close.png", 1)];
+        let inputs: Vec<_> = inputs.iter().collect();
+
+        let options = CodegenOptions {
+            split_root: true,
+            path_scheme: "rbxasset",
+            strict_names: false,
+        };
+
+        assert!(matches!(
+            perform_codegen(Some(Path::new("-")), &inputs, &options),
+            Err(CodegenError::SplitStdout)
+        ));
+    }
+}
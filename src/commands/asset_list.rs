@@ -1,10 +1,12 @@
 use std::collections::BTreeSet;
 use std::env;
 use std::io::{BufWriter, Write};
+use std::path::Path;
 
 use fs_err as fs;
 
-use crate::data::Manifest;
+use crate::commands::{SyncSession, SyncSettings};
+use crate::data::{AssetId, Manifest};
 use crate::options::{AssetListOptions, GlobalOptions};
 
 pub fn asset_list(_global: GlobalOptions, options: AssetListOptions) -> anyhow::Result<()> {
@@ -13,6 +15,10 @@ pub fn asset_list(_global: GlobalOptions, options: AssetListOptions) -> anyhow::
         None => env::current_dir()?,
     };
 
+    if options.verbose_list {
+        return verbose_asset_list(&project_path, &options.output);
+    }
+
     let manifest = Manifest::read_from_folder(&project_path)?;
 
     let mut asset_list = BTreeSet::new();
@@ -30,3 +36,92 @@ pub fn asset_list(_global: GlobalOptions, options: AssetListOptions) -> anyhow::
 
     Ok(())
 }
+
+/// Writes one line per uploaded input, since inputs packed into the same
+/// spritesheet share an ID but have their own sizes. Dimensions come from the
+/// input files, so they're left empty for files that aren't images.
+///
+/// The project is only read, so it isn't locked, and the hash cache isn't
+/// written back.
+fn verbose_asset_list(project_path: &Path, output: &Path) -> anyhow::Result<()> {
+    let settings = SyncSettings {
+        lock: false,
+        update_hash_cache: false,
+        ..SyncSettings::default()
+    };
+    let session = SyncSession::open(project_path, &settings)?;
+
+    let mut file = BufWriter::new(fs::File::create(output)?);
+    writeln!(file, "id\tname\twidth\theight\tdpi-scale")?;
+
+    for input in session.inputs() {
+        let id = match &input.id {
+            Some(AssetId::Id(id)) => id,
+            _ => continue,
+        };
+
+        let (width, height) = match input.dimensions {
+            Some((width, height)) => (width.to_string(), height.to_string()),
+            None => (String::new(), String::new()),
+        };
+
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}",
+            id, input.name, width, height, input.dpi_scale
+        )?;
+    }
+    file.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::{Rgba, RgbaImage};
+
+    use crate::sync_backend::DebugSyncBackend;
+
+    #[test]
+    fn verbose_list_only_reads_the_project() {
+        let root = env::temp_dir().join(format!("tarmac-test-asset-list-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::write(
+            root.join("tarmac.toml"),
+            "name = \"test\"\n[[inputs]]\nglob = \"assets/*.png\"\n",
+        )
+        .unwrap();
+        RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255]))
+            .save(root.join("assets/icon.png"))
+            .unwrap();
+
+        let mut backend = DebugSyncBackend::with_folder(root.join("debug"));
+        SyncSession::open(&root, &SyncSettings::default())
+            .unwrap()
+            .sync(&mut backend)
+            .unwrap();
+
+        let cache_path = root.join(".tarmac-hash-cache.json");
+        fs::remove_file(&cache_path).unwrap();
+        let manifest = fs::read(root.join("tarmac-manifest.toml")).unwrap();
+
+        let output = root.join("assets.tsv");
+        verbose_asset_list(&root, &output).unwrap();
+
+        let list = fs::read_to_string(&output).unwrap();
+        let lines: Vec<_> = list.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with("\tassets/icon.png\t3\t2\t1"));
+
+        assert!(!cache_path.exists());
+        assert_eq!(
+            fs::read(root.join("tarmac-manifest.toml")).unwrap(),
+            manifest
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use fs_err as fs;
+use image::{imageops, ImageFormat, RgbaImage};
+use rayon::{prelude::*, ThreadPoolBuilder};
+
+use crate::asset_name::AssetName;
+use crate::data::{ImageSlice, InputManifest, Manifest};
+use crate::options::{BackupOptions, GlobalOptions};
+use crate::roblox_web_api::RobloxApiClient;
+
+/// The inputs contained in each uploaded asset, by asset ID.
+type UploadedInputs<'a> = BTreeMap<u64, Vec<(&'a AssetName, &'a InputManifest)>>;
+
+pub fn backup(global: GlobalOptions, options: BackupOptions) -> anyhow::Result<()> {
+    let project_path = match options.project_path {
+        Some(path) => path,
+        None => env::current_dir()?,
+    };
+
+    let manifest = Manifest::read_from_folder(&project_path)?;
+
+    // Packed inputs share the ID of their spritesheet, so each asset is only
+    // downloaded once and then split back up into the inputs it contains.
+    let mut uploaded_inputs: UploadedInputs = BTreeMap::new();
+    for (name, input_manifest) in &manifest.inputs {
+        if let Some(id) = input_manifest.id {
+            uploaded_inputs
+                .entry(id)
+                .or_default()
+                .push((name, input_manifest));
+        }
+    }
+
+    let auth = global.auth();
+    let client_settings = global.client_settings();
+    let output_dir = &options.output_dir;
+
+    let failures = backup_assets(
+        &uploaded_inputs,
+        output_dir,
+        options.jobs,
+        || RobloxApiClient::new(auth.clone(), client_settings.clone()),
+        |api_client, id| Ok(api_client.download_image(id)?),
+    )?;
+
+    if failures > 0 {
+        anyhow::bail!(
+            "{} of {} assets couldn't be backed up",
+            failures,
+            uploaded_inputs.len()
+        );
+    }
+
+    log::info!(
+        "Backed up {} assets to {}",
+        uploaded_inputs.len(),
+        output_dir.display()
+    );
+
+    Ok(())
+}
+
+/// Downloads every uploaded asset with up to `jobs` threads and writes the
+/// inputs it contains under `output_dir`. Each thread gets its own client from
+/// `new_client` to download with.
+///
+/// Assets that fail are logged without stopping the others. Returns how many
+/// of them failed.
+fn backup_assets<C, E, N, D>(
+    uploaded_inputs: &UploadedInputs<'_>,
+    output_dir: &Path,
+    jobs: usize,
+    new_client: N,
+    download: D,
+) -> anyhow::Result<usize>
+where
+    E: std::fmt::Display,
+    N: Fn() -> Result<C, E> + Sync + Send,
+    D: Fn(&mut C, u64) -> anyhow::Result<Vec<u8>> + Sync + Send,
+{
+    let pool = ThreadPoolBuilder::new().num_threads(jobs.max(1)).build()?;
+
+    let failures = pool.install(|| {
+        uploaded_inputs
+            .par_iter()
+            .map_init(&new_client, |client, (&id, inputs)| {
+                let result = match client {
+                    Ok(client) => download(client, id)
+                        .and_then(|contents| write_asset(output_dir, id, &contents, inputs)),
+                    Err(err) => Err(anyhow::anyhow!("{}", err)),
+                };
+
+                if let Err(err) = &result {
+                    let names: Vec<_> = inputs.iter().map(|(name, _)| name.as_ref()).collect();
+                    log::error!(
+                        "Couldn't back up asset {} ({}): {:#}",
+                        id,
+                        names.join(", "),
+                        err
+                    );
+                }
+
+                result.is_err()
+            })
+            .filter(|&failed| failed)
+            .count()
+    });
+
+    Ok(failures)
+}
+
+/// Writes every input contained in a downloaded asset to its own path under
+/// `output_dir`.
+///
+/// Inputs are written in the format the asset was downloaded in, or as PNGs
+/// when they're cut out of a spritesheet, so their extension is changed to
+/// match. Roblox doesn't keep the original files that were uploaded.
+fn write_asset(
+    output_dir: &Path,
+    id: u64,
+    contents: &[u8],
+    inputs: &[(&AssetName, &InputManifest)],
+) -> anyhow::Result<()> {
+    let mut spritesheet = None;
+
+    for (name, input_manifest) in inputs {
+        let path = match input_manifest.image_slice() {
+            Some(slice) => {
+                if spritesheet.is_none() {
+                    let image = image::load_from_memory(contents)
+                        .with_context(|| format!("Asset {} isn't a valid image", id))?;
+                    spritesheet = Some(image.to_rgba8());
+                }
+
+                let path = backup_path(output_dir, name, Some(ImageFormat::Png));
+                create_parent_dir(&path)?;
+
+                let image = extract_slice(spritesheet.as_ref().unwrap(), slice);
+                image.save_with_format(&path, ImageFormat::Png)?;
+                path
+            }
+            None => {
+                let format = image::guess_format(contents).ok();
+                let path = backup_path(output_dir, name, format);
+                create_parent_dir(&path)?;
+
+                fs::write(&path, contents)?;
+                path
+            }
+        };
+
+        log::debug!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// The path an input is backed up to. If the format it's written in is known,
+/// the extension is replaced with that format's.
+fn backup_path(output_dir: &Path, name: &AssetName, format: Option<ImageFormat>) -> PathBuf {
+    let mut path = output_dir.join(name.as_ref());
+
+    if let Some(extension) = format.and_then(|format| format.extensions_str().first()) {
+        path.set_extension(extension);
+    }
+
+    path
+}
+
+fn create_parent_dir(path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(())
+}
+
+/// Cuts an input back out of the spritesheet it was packed into, undoing any
+/// rotation or trimming that was applied when it was packed.
+fn extract_slice(spritesheet: &RgbaImage, slice: ImageSlice) -> RgbaImage {
+    let (x, y) = slice.min();
+    let (width, height) = slice.size();
+    let mut image = imageops::crop_imm(spritesheet, x, y, width, height).to_image();
+
+    if slice.rotated() {
+        image = imageops::rotate270(&image);
+    }
+
+    if let Some(trim) = slice.trim() {
+        let (original_width, original_height) = trim.original_size;
+        let mut untrimmed = RgbaImage::new(original_width, original_height);
+        imageops::replace(&mut untrimmed, &image, trim.offset.0, trim.offset.1);
+        image = untrimmed;
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::Rgba;
+
+    use crate::data::ImageTrim;
+
+    #[test]
+    fn slices_are_rotated_and_untrimmed() {
+        // A 2x1 image that was trimmed from a 4x3 image, then rotated into a
+        // 1x2 slot at (1, 1) in the spritesheet.
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+
+        let mut spritesheet = RgbaImage::new(3, 3);
+        spritesheet.put_pixel(1, 1, red);
+        spritesheet.put_pixel(1, 2, blue);
+
+        let slice = ImageSlice::new((1, 1), (2, 3))
+            .with_rotated(true)
+            .with_trim(Some(ImageTrim {
+                offset: (1, 2),
+                original_size: (4, 3),
+            }));
+
+        let image = extract_slice(&spritesheet, slice);
+        assert_eq!(image.dimensions(), (4, 3));
+        assert_eq!(image.get_pixel(1, 2), &red);
+        assert_eq!(image.get_pixel(2, 2), &blue);
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn failed_downloads_are_counted_without_stopping() {
+        let output_dir = env::temp_dir().join(format!("tarmac-test-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+
+        let input = |id| InputManifest {
+            hash: String::new(),
+            id: Some(id),
+            slice: None,
+            rotated: false,
+            trim: None,
+            packable: false,
+            flipbook: false,
+        };
+        let name = |path: &str| AssetName::from_paths(Path::new(""), Path::new(path));
+        let (moderated_name, moderated) = (name("icons/moderated.jpg"), input(1));
+        let (kept_name, kept) = (name("icons/kept.jpg"), input(2));
+
+        let mut uploaded_inputs: UploadedInputs = BTreeMap::new();
+        uploaded_inputs.insert(1, vec![(&moderated_name, &moderated)]);
+        uploaded_inputs.insert(2, vec![(&kept_name, &kept)]);
+
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(RgbaImage::new(2, 2))
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+
+        let failures = backup_assets(
+            &uploaded_inputs,
+            &output_dir,
+            2,
+            || Ok::<_, String>(()),
+            |_, id| match id {
+                1 => anyhow::bail!("asset {} was moderated", id),
+                _ => Ok(png.clone()),
+            },
+        )
+        .unwrap();
+
+        // The downloaded PNG is written with an extension that matches it.
+        assert_eq!(failures, 1);
+        assert_eq!(fs::read(output_dir.join("icons/kept.png")).unwrap(), png);
+        assert!(!output_dir.join("icons/kept.jpg").exists());
+        assert!(!output_dir.join("icons/moderated.png").exists());
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -0,0 +1,25 @@
+use std::env;
+
+use crate::{
+    commands::{SyncError, SyncSession, SyncSettings},
+    options::{CodegenCommandOptions, GlobalOptions},
+};
+
+pub fn codegen(_global: GlobalOptions, options: CodegenCommandOptions) -> Result<(), SyncError> {
+    let fuzzy_config_path = match &options.config_path {
+        Some(path) => path.to_owned(),
+        None => env::current_dir()?,
+    };
+
+    let settings = SyncSettings {
+        lock: !options.no_lock,
+        update_hash_cache: false,
+        codegen_split_root: options.codegen_split_root,
+        ..SyncSettings::default()
+    };
+
+    let session = SyncSession::open(&fuzzy_config_path, &settings)?;
+    session.write_codegen()?;
+
+    Ok(())
+}
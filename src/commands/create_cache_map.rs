@@ -1,19 +1,23 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::env;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 
 use fs_err as fs;
+use path_slash::PathBufExt;
 
 use crate::asset_name::AssetName;
 use crate::data::Manifest;
-use crate::options::{CreateCacheMapOptions, GlobalOptions};
+use crate::options::{CacheMapFormat, CreateCacheMapOptions, GlobalOptions};
 use crate::roblox_web_api::RobloxApiClient;
 
 pub fn create_cache_map(
     global: GlobalOptions,
     options: CreateCacheMapOptions,
 ) -> anyhow::Result<()> {
-    let mut api_client = RobloxApiClient::new(global.auth);
+    let client_settings = global.client_settings();
+    let mut api_client = RobloxApiClient::new(global.auth(), client_settings)?;
 
     let project_path = match options.project_path {
         Some(path) => path,
@@ -44,13 +48,80 @@ pub fn create_cache_map(
             let path = options.cache_dir.join(id.to_string());
             fs::write(&path, contents)?;
 
-            index.insert(id, path.display().to_string());
+            let path = match options.format {
+                CacheMapFormat::Json => path.display().to_string(),
+                CacheMapFormat::Csv => slash_path(&path),
+            };
+
+            index.insert(id, path);
         }
     }
 
     let mut file = BufWriter::new(fs::File::create(&options.index_file)?);
-    serde_json::to_writer_pretty(&mut file, &index)?;
+
+    match options.format {
+        CacheMapFormat::Json => serde_json::to_writer_pretty(&mut file, &index)?,
+        CacheMapFormat::Csv => write_csv(&mut file, &index)?,
+    }
+
     file.flush()?;
 
     Ok(())
 }
+
+fn write_csv<W: Write>(output: &mut W, index: &BTreeMap<u64, String>) -> io::Result<()> {
+    writeln!(output, "asset_id,path")?;
+
+    for (id, path) in index {
+        writeln!(output, "{},{}", id, csv_field(path))?;
+    }
+
+    Ok(())
+}
+
+/// Writes a path with forward slashes. Paths that aren't valid Unicode can't be
+/// converted exactly, so any invalid parts are replaced instead.
+fn slash_path(path: &Path) -> String {
+    path.to_path_buf()
+        .to_slash()
+        .unwrap_or_else(|| path.to_string_lossy().replace('\\', "/"))
+}
+
+/// Quotes a CSV field if it contains any characters that would otherwise
+/// change the shape of the table.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn csv_has_header_and_quotes_paths() {
+        let mut index = BTreeMap::new();
+        index.insert(2, "assets/a, b.png".to_owned());
+        index.insert(1, "assets/foo.png".to_owned());
+
+        let mut output = Vec::new();
+        write_csv(&mut output, &index).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "asset_id,path\n1,assets/foo.png\n2,\"assets/a, b.png\"\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_that_arent_unicode_are_written_lossily() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = Path::new("cache").join(OsStr::from_bytes(b"sheet\xff"));
+        assert_eq!(slash_path(&path), "cache/sheet\u{fffd}");
+    }
+}
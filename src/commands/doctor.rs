@@ -0,0 +1,157 @@
+use std::env;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use fs_err as fs;
+use image::GenericImageView;
+
+use crate::{
+    data::{AssetCreator, AssetId},
+    image_encoding::decode_image,
+    options::{DoctorOptions, GlobalOptions},
+    sync_backend::{DebugSyncBackend, LocalSyncBackend, SyncBackend, UploadInfo},
+};
+
+/// A small image bundled with Tarmac, used to check decoding and syncing
+/// without depending on any project.
+static TEST_IMAGE: &[u8] = include_bytes!("fixtures/doctor.png");
+
+static NO_STUDIO: &str =
+    "Roblox Studio isn't installed. Pass --content-path to check another folder.";
+
+/// The outcome of a check that didn't fail.
+enum Outcome {
+    Passed(String),
+
+    /// The check couldn't be run on this machine, which isn't treated as a
+    /// failure.
+    Skipped(String),
+}
+
+pub fn doctor(global: GlobalOptions, options: DoctorOptions) -> anyhow::Result<()> {
+    let checks = [
+        ("Credentials", check_credentials(&global)),
+        (
+            "Local content folder",
+            check_content_path(options.content_path.as_deref()),
+        ),
+        ("Image decoding", check_image_decoding()),
+        ("Debug sync", check_debug_sync()),
+    ];
+
+    let mut failures = 0;
+
+    for (name, result) in &checks {
+        match result {
+            Ok(Outcome::Passed(detail)) => println!("[pass] {}: {}", name, detail),
+            Ok(Outcome::Skipped(reason)) => println!("[skip] {}: {}", name, reason),
+            Err(err) => {
+                failures += 1;
+                println!("[FAIL] {}: {:#}", name, err);
+            }
+        }
+    }
+
+    if failures > 0 {
+        bail!("{} of {} checks failed", failures, checks.len());
+    }
+
+    Ok(())
+}
+
+fn check_credentials(global: &GlobalOptions) -> anyhow::Result<Outcome> {
+    match global.credentials() {
+        Some((_, source)) => Ok(Outcome::Passed(format!("found a cookie from {}", source))),
+        None => {
+            bail!("no credentials found. Pass --auth or --auth-file, or log into Roblox Studio.")
+        }
+    }
+}
+
+fn check_content_path(content_path: Option<&Path>) -> anyhow::Result<Outcome> {
+    let backend = match content_path {
+        Some(content_path) => {
+            if !content_path.is_dir() {
+                bail!("{} isn't a folder", content_path.display());
+            }
+
+            LocalSyncBackend::with_content_path(content_path.to_owned(), None, false)
+        }
+        None => match LocalSyncBackend::new(None, false) {
+            Ok(backend) => backend,
+            Err(_) => return Ok(Outcome::Skipped(NO_STUDIO.to_owned())),
+        },
+    };
+
+    backend.check_writable()?;
+
+    Ok(Outcome::Passed(format!(
+        "{} is writable",
+        backend.content_path().display()
+    )))
+}
+
+fn check_image_decoding() -> anyhow::Result<Outcome> {
+    let image = decode_image(TEST_IMAGE, Path::new("doctor.png"), true)?;
+    let (width, height) = image.dimensions();
+
+    Ok(Outcome::Passed(format!(
+        "decoded a {}x{} PNG",
+        width, height
+    )))
+}
+
+/// Syncs the test image to the debug target in a temporary folder, then checks
+/// that what was written matches what was synced.
+fn check_debug_sync() -> anyhow::Result<Outcome> {
+    let folder = env::temp_dir().join(format!("tarmac-doctor-{}", std::process::id()));
+    let mut backend = DebugSyncBackend::with_folder(folder.clone());
+
+    let result = backend.upload(UploadInfo {
+        name: "doctor".to_owned(),
+        contents: TEST_IMAGE.to_vec(),
+        hash: blake3::hash(TEST_IMAGE).to_hex().to_string(),
+        source: None,
+        dpi_scale: 1,
+        creator: AssetCreator::User,
+    });
+
+    let written = match &result {
+        Ok(response) => match &response.id {
+            AssetId::Id(id) => fs::read(folder.join(id.to_string())).map_err(anyhow::Error::from),
+            other => Err(anyhow::anyhow!(
+                "expected a numeric asset ID, got {}",
+                other
+            )),
+        },
+        Err(_) => Ok(Vec::new()),
+    };
+
+    let _ = fs::remove_dir_all(&folder);
+
+    result.context("couldn't sync to the debug target")?;
+    if written? != TEST_IMAGE {
+        bail!("the synced image didn't match the original");
+    }
+
+    Ok(Outcome::Passed("synced a test image".to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bundled_image_decodes_and_syncs() {
+        assert!(matches!(check_image_decoding(), Ok(Outcome::Passed(_))));
+        assert!(matches!(check_debug_sync(), Ok(Outcome::Passed(_))));
+    }
+
+    #[test]
+    fn missing_content_folder_fails() {
+        let folder =
+            env::temp_dir().join(format!("tarmac-test-doctor-missing-{}", std::process::id()));
+
+        assert!(check_content_path(Some(&folder)).is_err());
+    }
+}
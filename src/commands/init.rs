@@ -0,0 +1,120 @@
+use std::env;
+
+use anyhow::bail;
+use fs_err as fs;
+
+use crate::options::{GlobalOptions, InitOptions};
+
+static CONFIG_FILENAME: &str = "tarmac.toml";
+
+pub fn init(_global: GlobalOptions, options: InitOptions) -> anyhow::Result<()> {
+    let project_path = match options.project_path {
+        Some(path) => path,
+        None => env::current_dir()?,
+    };
+
+    let config_path = project_path.join(CONFIG_FILENAME);
+
+    if config_path.exists() && !options.force {
+        bail!(
+            "{} already exists. Use --force to overwrite it.",
+            config_path.display()
+        );
+    }
+
+    // The project's folder needs to exist before we can find its name.
+    fs::create_dir_all(&project_path)?;
+
+    let name = match &options.name {
+        Some(name) => name.clone(),
+        None => project_path
+            .canonicalize()?
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("tarmac-project")
+            .to_owned(),
+    };
+
+    fs::create_dir_all(project_path.join("assets"))?;
+    fs::write(&config_path, render_config(&name))?;
+
+    log::info!("Created {}", config_path.display());
+    log::info!("Put images in the assets folder, then run `tarmac sync --target roblox`.");
+
+    Ok(())
+}
+
+/// Creates the contents of a starter config for a project with the given name.
+fn render_config(name: &str) -> String {
+    // Going through toml::Value escapes any characters that aren't allowed in
+    // a TOML string.
+    let name = toml::Value::String(name.to_owned());
+
+    format!(
+        r#"# tarmac.toml is Tarmac's configuration file.
+# It tells Tarmac how to handle the assets in your project.
+#
+# Sync your assets with:
+#     tarmac sync --target roblox
+
+name = {name}
+
+# Uncomment to upload assets to a group instead of your account.
+# upload-to-group-id = 0
+
+# Inputs tell Tarmac where to find assets that you'll use in your game.
+[[inputs]]
+glob = "assets/**/*.png"
+
+# Generate links to your assets in src/assets.lua.
+codegen = true
+codegen-path = "src/assets.lua"
+codegen-base-path = "assets"
+
+# Uncomment to allow images to be packed into spritesheets.
+# packable = true
+"#,
+        name = name
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use structopt::StructOpt;
+
+    use crate::data::Config;
+
+    #[test]
+    fn starter_config_is_valid() {
+        let config: Config = toml::from_str(&render_config("my \"quoted\" game")).unwrap();
+
+        assert_eq!(config.name, "my \"quoted\" game");
+        assert_eq!(config.inputs.len(), 1);
+        assert!(config.inputs[0].codegen);
+    }
+
+    #[test]
+    fn creates_missing_project_folder() {
+        let parent = env::temp_dir().join(format!("tarmac-test-init-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&parent);
+        let project_path = parent.join("my-game");
+
+        init(
+            GlobalOptions::from_iter(&["tarmac"]),
+            InitOptions {
+                project_path: Some(project_path.clone()),
+                name: None,
+                force: false,
+            },
+        )
+        .unwrap();
+
+        let config = Config::read_from_folder(&project_path).unwrap();
+        assert_eq!(config.name, "my-game");
+        assert!(project_path.join("assets").is_dir());
+
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
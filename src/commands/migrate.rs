@@ -0,0 +1,37 @@
+use std::env;
+
+use crate::{
+    data::{Manifest, MANIFEST_VERSION},
+    options::{GlobalOptions, MigrateOptions},
+    project_lock::ProjectLock,
+};
+
+pub fn migrate(_global: GlobalOptions, options: MigrateOptions) -> anyhow::Result<()> {
+    let project_path = match options.project_path {
+        Some(path) => path,
+        None => env::current_dir()?,
+    };
+
+    let _lock = if options.no_lock {
+        None
+    } else {
+        Some(ProjectLock::acquire(&project_path)?)
+    };
+
+    let manifest = Manifest::read_from_folder(&project_path)?;
+
+    if !manifest.is_outdated() {
+        log::info!("Manifest is already version {}", MANIFEST_VERSION);
+        return Ok(());
+    }
+
+    manifest.write_to_folder(&project_path)?;
+
+    log::info!(
+        "Migrated manifest from version {} to version {}",
+        manifest.version,
+        MANIFEST_VERSION
+    );
+
+    Ok(())
+}
@@ -1,9 +1,21 @@
 mod asset_list;
+mod backup;
+mod codegen;
 mod create_cache_map;
+mod doctor;
+mod init;
+mod migrate;
 mod sync;
 mod upload_image;
+mod whoami;
 
 pub use asset_list::*;
+pub use backup::*;
+pub use codegen::*;
 pub use create_cache_map::*;
+pub use doctor::*;
+pub use init::*;
+pub use migrate::*;
 pub use sync::*;
 pub use upload_image::*;
+pub use whoami::*;
@@ -1,36 +1,38 @@
 use fs_err as fs;
 
-use image::{codecs::png::PngEncoder, GenericImageView};
+use std::{
+    borrow::Cow,
+    io::{self, Read},
+    path::Path,
+};
 
-use std::borrow::Cow;
+use anyhow::{bail, Context};
 
 use crate::{
-    alpha_bleed::alpha_bleed,
-    auth_cookie::get_auth_cookie,
+    alpha_bleed::{alpha_bleed, BleedChannels},
+    image_encoding::{decode_image, encode_png},
     options::{GlobalOptions, UploadImageOptions},
     roblox_web_api::{ImageUploadData, RobloxApiClient},
 };
 
-pub fn upload_image(global: GlobalOptions, options: UploadImageOptions) {
-    let auth = global
-        .auth
-        .or_else(get_auth_cookie)
-        .expect("no auth cookie found");
-
-    let image_data = fs::read(options.path).expect("couldn't read input file");
+pub fn upload_image(global: GlobalOptions, options: UploadImageOptions) -> anyhow::Result<()> {
+    let client_settings = global.client_settings();
+    let quiet = global.quiet;
+    let auth = match global.credentials() {
+        Some((auth, _)) => auth,
+        None => bail!("No auth cookie found. Pass one with --auth or --auth-file."),
+    };
 
-    let mut img = image::load_from_memory(&image_data).expect("couldn't load image");
+    let image_data = read_image_data(&options.path)?;
 
-    alpha_bleed(&mut img);
+    let mut img = decode_image(&image_data, &options.path, !options.no_exif_rotate)
+        .context("Couldn't load image")?;
 
-    let (width, height) = img.dimensions();
+    alpha_bleed(&mut img, BleedChannels::default(), None);
 
-    let mut encoded_image: Vec<u8> = Vec::new();
-    PngEncoder::new(&mut encoded_image)
-        .encode(&img.to_bytes(), width, height, img.color())
-        .unwrap();
+    let encoded_image = encode_png(&img, options.encode_indexed)?;
 
-    let mut client = RobloxApiClient::new(Some(auth));
+    let mut client = RobloxApiClient::new(Some(auth), client_settings)?;
 
     let upload_data = ImageUploadData {
         image_data: Cow::Owned(encoded_image.to_vec()),
@@ -39,10 +41,31 @@ pub fn upload_image(global: GlobalOptions, options: UploadImageOptions) {
         group_id: None,
     };
 
-    let response = client
-        .upload_image(upload_data)
-        .expect("Roblox API request failed");
+    let response = client.upload_image(upload_data)?;
 
-    eprintln!("Image uploaded successfully!");
+    if !quiet {
+        eprintln!("Image uploaded successfully!");
+    }
     println!("{}", response.backing_asset_id);
+
+    Ok(())
+}
+
+/// Reads the image to upload from the given path, or from stdin if the path
+/// is `-`.
+fn read_image_data(path: &Path) -> anyhow::Result<Vec<u8>> {
+    if path != Path::new("-") {
+        return Ok(fs::read(path)?);
+    }
+
+    let mut image_data = Vec::new();
+    io::stdin()
+        .read_to_end(&mut image_data)
+        .context("Couldn't read image from stdin")?;
+
+    if image_data.is_empty() {
+        bail!("No image data was written to stdin");
+    }
+
+    Ok(image_data)
 }
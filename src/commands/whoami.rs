@@ -0,0 +1,35 @@
+use anyhow::{bail, Context};
+use reqwest::StatusCode;
+
+use crate::{
+    options::GlobalOptions,
+    roblox_web_api::{RobloxApiClient, RobloxApiError},
+};
+
+pub fn whoami(global: GlobalOptions) -> anyhow::Result<()> {
+    let (auth, source) = match global.credentials() {
+        Some(credentials) => credentials,
+        None => bail!("Tarmac has no credentials to use. Pass --auth or --auth-file, or log into Roblox Studio."),
+    };
+
+    let mut api_client = RobloxApiClient::new(Some(auth), global.client_settings())?;
+
+    let user = match api_client.get_authenticated_user() {
+        Ok(user) => user,
+        Err(RobloxApiError::ResponseError {
+            status: StatusCode::UNAUTHORIZED,
+            ..
+        }) => bail!(
+            "The cookie from {} isn't valid. It may have expired or been logged out.",
+            source
+        ),
+        Err(err) => return Err(err).context("Couldn't look up the authenticated user"),
+    };
+
+    println!(
+        "Logged in as {} (@{}, user ID {}) using the cookie from {}.",
+        user.display_name, user.name, user.id, source
+    );
+
+    Ok(())
+}
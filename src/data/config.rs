@@ -7,9 +7,11 @@ use fs_err as fs;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::glob::Glob;
+use crate::{alpha_bleed::BleedChannels, data::DEFAULT_PATH_SCHEME, glob::Glob};
 
 static CONFIG_FILENAME: &str = "tarmac.toml";
+static JSON_CONFIG_FILENAME: &str = "tarmac.json";
+static OVERRIDES_FILENAME: &str = "tarmac-overrides.toml";
 
 /// Configuration for Tarmac, contained in a tarmac.toml file.
 ///
@@ -26,11 +28,70 @@ pub struct Config {
     #[serde(default = "default_max_spritesheet_size")]
     pub max_spritesheet_size: (u32, u32),
 
-    /// The padding size that any packed spritesheets should use. Only applies if
-    /// this config is the root config file.
+    /// The number of pixels of padding placed after each image in packed
+    /// spritesheets. Only applies if this config is the root config file.
     #[serde(default = "default_spritesheet_padding_size")]
     pub spritesheet_padding_size: u32,
 
+    /// Whether padding is placed on every side of each packed image, including
+    /// along spritesheet edges, instead of only to its right and below it. Only
+    /// applies if this config is the root config file.
+    #[serde(default)]
+    pub spritesheet_symmetric_padding: bool,
+
+    /// Whether images may be rotated by 90 degrees to pack spritesheets more
+    /// tightly. Rotated images are marked in generated code, so this should
+    /// only be enabled if all consumers know how to handle them. Only applies
+    /// if this config is the root config file.
+    #[serde(default)]
+    pub spritesheet_allow_rotation: bool,
+
+    /// The algorithm used to decide where images go in packed spritesheets.
+    /// Only applies if this config is the root config file.
+    #[serde(default)]
+    pub spritesheet_pack_algorithm: PackAlgorithm,
+
+    /// Whether the width and height of packed spritesheets should be rounded up
+    /// to the next power of two, padding them with transparent pixels. It's an
+    /// error for a rounded size to be larger than `max_spritesheet_size`. Only
+    /// applies if this config is the root config file.
+    #[serde(default)]
+    pub spritesheet_power_of_two: bool,
+
+    /// Whether to crop the transparent border around each image before it's
+    /// packed into a spritesheet. The crop is recorded with the image's slice
+    /// so that its original size can be reconstructed. Only applies if this
+    /// config is the root config file.
+    #[serde(default)]
+    pub spritesheet_trim: bool,
+
+    /// Whether only images from the same folder may be packed into the same
+    /// spritesheet. Only applies if this config is the root config file.
+    #[serde(default)]
+    pub spritesheet_group_by_folder: bool,
+
+    /// The largest width or height an image may be uploaded with. Larger
+    /// images are scaled down to fit before they're uploaded or packed,
+    /// keeping their aspect ratio. Defaults to 1024, the largest size Roblox
+    /// displays images at. Only applies if this config is the root config
+    /// file.
+    #[serde(default = "default_max_image_dimension")]
+    pub max_image_dimension: u32,
+
+    /// The name packed spritesheets are uploaded with. `{index}` is replaced
+    /// with a number unique to each spritesheet, and `{group}` with the name
+    /// of the folder containing all of its images. Only applies if this config
+    /// is the root config file.
+    #[serde(default = "default_spritesheet_name_template")]
+    pub spritesheet_name_template: String,
+
+    /// An RGBA color used to fill the space around images in packed
+    /// spritesheets, so that their boundaries are easy to see. Only used by
+    /// the `debug` sync target, and only applies if this config is the root
+    /// config file.
+    #[serde(default)]
+    pub spritesheet_debug_background: Option<[u8; 4]>,
+
     /// A path to a folder where any assets contained in the project should be
     /// stored. Each asset's name will match its asset ID.
     pub asset_cache_path: Option<PathBuf>,
@@ -39,11 +100,74 @@ pub struct Config {
     /// referred to by this project.
     pub asset_list_path: Option<PathBuf>,
 
+    /// A path to a file where Tarmac will write a sprite atlas descriptor in
+    /// TexturePacker's JSON format, describing every packed spritesheet.
+    pub atlas_descriptor_path: Option<PathBuf>,
+
+    /// Whether `tarmac sync` should generate code for inputs with a
+    /// `codegen-path` or `codegen` enabled. `tarmac codegen` generates code
+    /// either way. Only applies if this config is the root config file.
+    #[serde(default = "default_codegen")]
+    pub codegen: bool,
+
+    /// Whether generated code with names that aren't valid Lua identifiers,
+    /// which can only be accessed with brackets, is an error instead of a
+    /// warning. Only applies if this config is the root config file.
+    #[serde(default)]
+    pub codegen_strict_names: bool,
+
     /// If specified, requires that all uploaded assets are uploaded to the
     /// given group. Attempting to sync will fail if the authenticated user does
-    /// not have access to create assets on the group.
+    /// not have access to create assets on the group. Inputs can override this
+    /// with their own `upload-to-group-id` or `upload-to-user`.
     pub upload_to_group_id: Option<u64>,
 
+    /// If true, unpacked images with identical contents are only uploaded once
+    /// and share the resulting asset ID. Only applies if this config is the
+    /// root config file.
+    #[serde(default)]
+    pub dedupe_uploads: bool,
+
+    /// If true, the `local` target will write a `<name>.meta.json` file next
+    /// to each asset it writes, describing where the asset came from.
+    #[serde(default)]
+    pub local_emit_metadata: bool,
+
+    /// If true, the `local` target will also write inputs that were already
+    /// uploaded to Roblox, so that generated code refers to every input by
+    /// its content path.
+    #[serde(default)]
+    pub local_codegen_content_paths: bool,
+
+    /// If true, Tarmac will record the ID of the Decal asset created by an
+    /// upload instead of the ID of the Image asset backing it. This ID is what
+    /// ends up in the manifest and in generated code.
+    #[serde(default)]
+    pub use_decal_id: bool,
+
+    /// If true, the description of each asset uploaded to Roblox ends with the
+    /// hash of the contents it was uploaded from.
+    #[serde(default)]
+    pub include_hash_in_description: bool,
+
+    /// The URI scheme that generated code uses to refer to assets on the local
+    /// disk, like the ones written by the `local` target. An empty string
+    /// leaves them as bare paths. Only applies if this config is the root
+    /// config file.
+    #[serde(default = "default_path_uri_scheme")]
+    pub path_uri_scheme: String,
+
+    /// The name to give an asset instead if Roblox rejects its name as
+    /// inappropriate. Only applies if this config is the root config file.
+    #[serde(default = "default_moderation_fallback_name")]
+    pub moderation_fallback_name: String,
+
+    /// How many times an upload may be retried with `moderation-fallback-name`
+    /// after its name is rejected as inappropriate. Only applies if this config
+    /// is the root config file.
+    #[serde(default = "default_moderation_retries")]
+    pub moderation_retries: u32,
+
     /// A list of paths that Tarmac should search in to find other Tarmac
     /// projects.
     ///
@@ -56,6 +180,22 @@ pub struct Config {
     #[serde(default)]
     pub inputs: Vec<InputConfig>,
 
+    /// A list of globs that files must match at least one of to be used as
+    /// inputs, in addition to matching one of this config's inputs. Like input
+    /// globs, these are relative to the folder containing this config. If
+    /// empty, all files are allowed.
+    ///
+    /// The root config's `include` and `exclude` also apply to the inputs of
+    /// every other config inside its folder.
+    #[serde(default)]
+    pub include: Vec<Glob>,
+
+    /// A list of globs matching files that Tarmac should ignore, even if they
+    /// match one of this config's inputs. Like input globs, these are relative
+    /// to the folder containing this config.
+    #[serde(default)]
+    pub exclude: Vec<Glob>,
+
     /// The path that this config came from. Paths from this config should be
     /// relative to the folder containing this file.
     #[serde(skip)]
@@ -74,27 +214,139 @@ impl Config {
         }
     }
 
+    /// Reads the config in the given folder, which is `tarmac.toml`, or
+    /// `tarmac.json` if there's no `tarmac.toml`.
     pub fn read_from_folder<P: AsRef<Path>>(folder_path: P) -> Result<Self, ConfigError> {
         let folder_path = folder_path.as_ref();
-        let file_path = &folder_path.join(CONFIG_FILENAME);
 
-        Self::read_from_file(file_path)
+        match Self::read_from_file(folder_path.join(CONFIG_FILENAME)) {
+            Err(err) if err.is_not_found() => {
+                Self::read_from_file(folder_path.join(JSON_CONFIG_FILENAME))
+            }
+            result => result,
+        }
     }
 
+    /// Reads the config at the given path, as TOML or JSON depending on its
+    /// extension.
     pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
         let path = path.as_ref();
         let contents = fs::read(path)?;
 
-        let mut config: Self = toml::from_slice(&contents).map_err(|source| ConfigError::Toml {
-            source,
-            path: path.to_owned(),
-        })?;
+        let mut config = Self::from_slice(&contents, path)?;
         config.file_path = path.to_owned();
+        config.validate()?;
         config.make_paths_absolute();
 
         Ok(config)
     }
 
+    fn from_slice(contents: &[u8], path: &Path) -> Result<Self, ConfigError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_slice(contents).map_err(|source| ConfigError::Toml {
+                source,
+                path: path.to_owned(),
+            }),
+            Some("json") => serde_json::from_slice(contents).map_err(|source| ConfigError::Json {
+                source,
+                path: path.to_owned(),
+            }),
+            _ => Err(ConfigError::UnknownFormat {
+                path: path.to_owned(),
+            }),
+        }
+    }
+
+    /// Tells whether a file passes this config's `include` and `exclude`
+    /// globs. The path should be relative to the folder containing this
+    /// config.
+    pub fn allows_path(&self, path: &Path) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(path));
+
+        included && !self.exclude.iter().any(|glob| glob.is_match(path))
+    }
+
+    /// Who assets should be uploaded as, unless an input says otherwise.
+    pub fn upload_creator(&self) -> AssetCreator {
+        match self.upload_to_group_id {
+            Some(group_id) => AssetCreator::Group(group_id),
+            None => AssetCreator::User,
+        }
+    }
+
+    /// Check for combinations of options that can't be expressed by the
+    /// format alone.
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.validate_spritesheet_name_template()?;
+
+        if self.max_image_dimension == 0 {
+            return Err(ConfigError::InvalidMaxImageDimension {
+                path: self.file_path.clone(),
+            });
+        }
+
+        for input in &self.inputs {
+            if input.upload_to_group_id.is_some() && input.upload_to_user {
+                return Err(ConfigError::AmbiguousCreatorType {
+                    path: self.file_path.clone(),
+                    glob: input.glob.to_string(),
+                });
+            }
+
+            if input.auto_dpi_variants.contains(&0) {
+                return Err(ConfigError::InvalidDpiScale {
+                    path: self.file_path.clone(),
+                    glob: input.glob.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the spritesheet name template only uses placeholders Tarmac
+    /// knows about, and that it gives every spritesheet a different name.
+    fn validate_spritesheet_name_template(&self) -> Result<(), ConfigError> {
+        let template = &self.spritesheet_name_template;
+        let mut has_index = false;
+        let mut rest = template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            let after = &rest[start + 1..];
+            let end = after
+                .find('}')
+                .ok_or_else(|| ConfigError::UnclosedTemplatePlaceholder {
+                    path: self.file_path.clone(),
+                    template: template.clone(),
+                })?;
+            let placeholder = &after[..end];
+
+            match placeholder {
+                "index" => has_index = true,
+                "group" => {}
+                _ => {
+                    return Err(ConfigError::UnknownTemplatePlaceholder {
+                        path: self.file_path.clone(),
+                        template: template.clone(),
+                        placeholder: placeholder.to_owned(),
+                    })
+                }
+            }
+
+            rest = &after[end + 1..];
+        }
+
+        if !has_index {
+            return Err(ConfigError::NonUniqueSpritesheetNames {
+                path: self.file_path.clone(),
+                template: template.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
     /// The path that paths in this Config should be considered relative to.
     pub fn folder(&self) -> &Path {
         self.file_path.parent().unwrap()
@@ -108,6 +360,10 @@ impl Config {
             make_absolute(list_path, base);
         }
 
+        if let Some(atlas_path) = self.atlas_descriptor_path.as_mut() {
+            make_absolute(atlas_path, base);
+        }
+
         if let Some(cache_path) = self.asset_cache_path.as_mut() {
             make_absolute(cache_path, base);
         }
@@ -118,7 +374,10 @@ impl Config {
 
         for input in &mut self.inputs {
             if let Some(codegen_path) = input.codegen_path.as_mut() {
-                make_absolute(codegen_path, base);
+                // `-` stands for stdout rather than a file in this folder.
+                if codegen_path.as_os_str() != "-" {
+                    make_absolute(codegen_path, base);
+                }
             }
 
             make_absolute(&mut input.codegen_base_path, base);
@@ -132,6 +391,28 @@ fn default_max_spritesheet_size() -> (u32, u32) {
 fn default_spritesheet_padding_size() -> u32 {
     1
 }
+fn default_spritesheet_name_template() -> String {
+    "spritesheet-{index}".to_owned()
+}
+fn default_flipbook_fps() -> u32 {
+    30
+}
+fn default_codegen() -> bool {
+    true
+}
+fn default_path_uri_scheme() -> String {
+    DEFAULT_PATH_SCHEME.to_owned()
+}
+fn default_moderation_fallback_name() -> String {
+    "image".to_owned()
+}
+fn default_moderation_retries() -> u32 {
+    1
+}
+
+fn default_max_image_dimension() -> u32 {
+    1024
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
@@ -146,13 +427,28 @@ pub struct InputConfig {
     pub codegen: bool,
 
     /// If specified, batches together all of the generated code for this group
-    /// of inputs into a single file created at this path.
+    /// of inputs into a single file created at this path, or written to stdout
+    /// if the path is `-`.
     #[serde(default)]
     pub codegen_path: Option<PathBuf>,
 
     #[serde(default)]
     pub codegen_base_path: PathBuf,
 
+    /// If specified, the name these inputs are given in code generated at
+    /// `codegen_path`, instead of their file names. They keep the folders
+    /// they're in. It's an error for two inputs to end up with the same name,
+    /// so this is usually given to inputs whose glob matches a single file.
+    #[serde(default)]
+    pub codegen_name: Option<String>,
+
+    /// If true, generated code for inputs that aren't packed into a
+    /// spritesheet is a table with `ImageRectOffset` and `ImageRectSize`
+    /// covering the whole image, just like packed inputs, instead of a plain
+    /// asset URL.
+    #[serde(default)]
+    pub codegen_uniform_sprites: bool,
+
     /// Whether the assets affected by this config are allowed to be packed into
     /// spritesheets.
     ///
@@ -162,6 +458,209 @@ pub struct InputConfig {
     /// instances.
     #[serde(default)]
     pub packable: bool,
+
+    /// If true, these inputs are frames of flipbook animations. The frames in
+    /// each folder are packed in numeric order into a uniform grid on a
+    /// spritesheet of their own, and generated code describes the folder as
+    /// one flipbook.
+    #[serde(default)]
+    pub flipbook: bool,
+
+    /// The frame rate that generated code gives these inputs' flipbooks.
+    #[serde(default = "default_flipbook_fps")]
+    pub flipbook_fps: u32,
+
+    /// The color channels that alpha bleeding may modify for these inputs.
+    ///
+    /// Images that store data instead of color, like normal maps, can set this
+    /// to an empty string to disable alpha bleeding entirely.
+    #[serde(default)]
+    pub alpha_bleed_channels: BleedChannels,
+
+    /// How many pixels out from the edge of the opaque parts of these images
+    /// alpha bleeding should reach. If unset, every transparent pixel is
+    /// filled in.
+    ///
+    /// Wider bleeding helps with artifacts at lower mip levels, but values
+    /// above about 4 rarely help and make bleeding slower.
+    #[serde(default)]
+    pub alpha_bleed_distance: Option<u32>,
+
+    /// Whether images in this input group that are entirely transparent should
+    /// be left out of the sync instead of being uploaded. Packed images always
+    /// stay on their spritesheets.
+    #[serde(default)]
+    pub skip_fully_transparent: bool,
+
+    /// Whether images in this input group with at most 256 colors should be
+    /// uploaded as indexed PNGs, which are much smaller for flat art like
+    /// icons. Images with more colors are uploaded in full color. Packed images
+    /// are only placed in spritesheets with other inputs that set this the same
+    /// way.
+    #[serde(default)]
+    pub encode_indexed: bool,
+
+    /// DPI scales that Tarmac should generate for these inputs by downscaling
+    /// the highest-scale version of each image. Scales that already have an
+    /// image on disk, or that are higher than the highest scale found, are
+    /// skipped.
+    ///
+    /// For example, with `[1, 2]`, `icon@3x.png` is used to generate both
+    /// `icon.png` and `icon@2x.png`.
+    #[serde(default)]
+    pub auto_dpi_variants: Vec<u32>,
+
+    /// If specified, these inputs are uploaded to the given group instead of
+    /// the one given by the root config's `upload-to-group-id`.
+    #[serde(default)]
+    pub upload_to_group_id: Option<u64>,
+
+    /// If true, these inputs are uploaded to the authenticated user even if
+    /// the root config specifies `upload-to-group-id`.
+    #[serde(default)]
+    pub upload_to_user: bool,
+}
+
+impl InputConfig {
+    /// Who these inputs should be uploaded as, given the creator that the
+    /// root config picked.
+    pub fn upload_creator(&self, default: AssetCreator) -> AssetCreator {
+        if let Some(group_id) = self.upload_to_group_id {
+            AssetCreator::Group(group_id)
+        } else if self.upload_to_user {
+            AssetCreator::User
+        } else {
+            default
+        }
+    }
+}
+
+/// Input options for every file in a folder and its subfolders, contained in a
+/// tarmac-overrides.toml file.
+///
+/// Overrides change the options of inputs found by a config in a parent
+/// folder, without defining any new inputs. Each option that's set replaces the
+/// one from the input's config, and overrides in deeper folders win over ones
+/// in shallower folders.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct InputOverrides {
+    pub codegen: Option<bool>,
+    pub codegen_uniform_sprites: Option<bool>,
+    pub packable: Option<bool>,
+    pub flipbook: Option<bool>,
+    pub flipbook_fps: Option<u32>,
+    pub alpha_bleed_channels: Option<BleedChannels>,
+    pub alpha_bleed_distance: Option<u32>,
+    pub skip_fully_transparent: Option<bool>,
+    pub encode_indexed: Option<bool>,
+    pub auto_dpi_variants: Option<Vec<u32>>,
+    pub upload_to_group_id: Option<u64>,
+    pub upload_to_user: Option<bool>,
+}
+
+impl InputOverrides {
+    /// Reads the overrides in the given folder, if it has any.
+    pub fn read_from_folder<P: AsRef<Path>>(folder_path: P) -> Result<Option<Self>, ConfigError> {
+        let path = folder_path.as_ref().join(OVERRIDES_FILENAME);
+
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let overrides: Self = toml::from_slice(&contents).map_err(|source| ConfigError::Toml {
+            source,
+            path: path.clone(),
+        })?;
+
+        if overrides.upload_to_group_id.is_some() && overrides.upload_to_user == Some(true) {
+            return Err(ConfigError::AmbiguousOverrideCreatorType { path });
+        }
+
+        if matches!(&overrides.auto_dpi_variants, Some(scales) if scales.contains(&0)) {
+            return Err(ConfigError::InvalidOverrideDpiScale { path });
+        }
+
+        Ok(Some(overrides))
+    }
+
+    /// Replaces the options of `input` that these overrides set.
+    pub fn apply(&self, input: &mut InputConfig) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
+        }
+
+        set(&mut input.codegen, &self.codegen);
+        set(
+            &mut input.codegen_uniform_sprites,
+            &self.codegen_uniform_sprites,
+        );
+        set(&mut input.packable, &self.packable);
+        set(&mut input.flipbook, &self.flipbook);
+        set(&mut input.flipbook_fps, &self.flipbook_fps);
+        set(&mut input.alpha_bleed_channels, &self.alpha_bleed_channels);
+        set(
+            &mut input.skip_fully_transparent,
+            &self.skip_fully_transparent,
+        );
+        set(&mut input.encode_indexed, &self.encode_indexed);
+        set(&mut input.auto_dpi_variants, &self.auto_dpi_variants);
+
+        if self.alpha_bleed_distance.is_some() {
+            input.alpha_bleed_distance = self.alpha_bleed_distance;
+        }
+
+        // Picking one creator replaces whichever one was picked before, so
+        // that inputs never end up with both.
+        if let Some(group_id) = self.upload_to_group_id {
+            input.upload_to_group_id = Some(group_id);
+            input.upload_to_user = false;
+        }
+
+        if let Some(upload_to_user) = self.upload_to_user {
+            input.upload_to_user = upload_to_user;
+            if upload_to_user {
+                input.upload_to_group_id = None;
+            }
+        }
+    }
+}
+
+/// The algorithms that can be used to pack spritesheets. See
+/// `packos::PackAlgorithm` for how each of them works.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackAlgorithm {
+    #[default]
+    Simple,
+    Shelf,
+    Skyline,
+    MaxRects,
+}
+
+impl From<PackAlgorithm> for packos::PackAlgorithm {
+    fn from(algorithm: PackAlgorithm) -> Self {
+        match algorithm {
+            PackAlgorithm::Simple => Self::Simple,
+            PackAlgorithm::Shelf => Self::Shelf,
+            PackAlgorithm::Skyline => Self::Skyline,
+            PackAlgorithm::MaxRects => Self::MaxRects,
+        }
+    }
+}
+
+/// The owner of an uploaded asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetCreator {
+    /// The user that Tarmac is authenticated as.
+    User,
+
+    /// A group that the authenticated user can create assets for.
+    Group(u64),
 }
 
 #[derive(Debug, Error)]
@@ -172,6 +671,67 @@ pub enum ConfigError {
         source: toml::de::Error,
     },
 
+    #[error("Error deserializing JSON from path {}", .path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error(
+        "Config {} isn't a TOML or JSON file. Tarmac configs must end in .toml or .json.",
+        .path.display()
+    )]
+    UnknownFormat { path: PathBuf },
+
+    #[error(
+        "Input with glob {glob} in config {} specifies both upload-to-group-id and upload-to-user",
+        .path.display()
+    )]
+    AmbiguousCreatorType { path: PathBuf, glob: String },
+
+    #[error(
+        "Input with glob {glob} in config {} lists a DPI scale of 0 in auto-dpi-variants",
+        .path.display()
+    )]
+    InvalidDpiScale { path: PathBuf, glob: String },
+
+    #[error("max-image-dimension in config {} must be at least 1", .path.display())]
+    InvalidMaxImageDimension { path: PathBuf },
+
+    #[error(
+        "Overrides {} specify both upload-to-group-id and upload-to-user",
+        .path.display()
+    )]
+    AmbiguousOverrideCreatorType { path: PathBuf },
+
+    #[error(
+        "Overrides {} list a DPI scale of 0 in auto-dpi-variants",
+        .path.display()
+    )]
+    InvalidOverrideDpiScale { path: PathBuf },
+
+    #[error(
+        "spritesheet-name-template \"{template}\" in config {} uses the unknown placeholder {{{placeholder}}}. Only {{index}} and {{group}} are supported.",
+        .path.display()
+    )]
+    UnknownTemplatePlaceholder {
+        path: PathBuf,
+        template: String,
+        placeholder: String,
+    },
+
+    #[error(
+        "spritesheet-name-template \"{template}\" in config {} has a {{ that is never closed.",
+        .path.display()
+    )]
+    UnclosedTemplatePlaceholder { path: PathBuf, template: String },
+
+    #[error(
+        "spritesheet-name-template \"{template}\" in config {} must contain {{index}} so that every spritesheet gets its own name",
+        .path.display()
+    )]
+    NonUniqueSpritesheetNames { path: PathBuf, template: String },
+
     #[error(transparent)]
     Io {
         #[from]
@@ -200,3 +760,82 @@ fn make_absolute(path: &mut PathBuf, base: &Path) {
         *path = new_path;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_error(source: &str) -> String {
+        toml::from_str::<Config>(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn unknown_config_keys_are_rejected() {
+        let message = parse_error("name = \"test\"\nspritesheet_paddingsize = 2\n");
+
+        assert!(message.contains("unknown field `spritesheet_paddingsize`"));
+        assert!(message.contains("`spritesheet-padding-size`"));
+    }
+
+    #[test]
+    fn unknown_input_keys_are_rejected() {
+        let message = parse_error(
+            "name = \"test\"\n\n[[inputs]]\nglob = \"*.png\"\ncodegen_path = \"a.lua\"\n",
+        );
+
+        assert!(message.contains("unknown field `codegen_path`"));
+        assert!(message.contains("`codegen-path`"));
+        assert!(message.contains("for key `inputs`"));
+    }
+
+    #[test]
+    fn json_configs_are_read_like_toml() {
+        let toml = Config::from_slice(
+            b"name = \"test\"\nmax-spritesheet-size = [512, 512]\n\n[[inputs]]\nglob = \"assets/*.png\"\npackable = true\nalpha-bleed-channels = \"rg\"\n",
+            Path::new("tarmac.toml"),
+        )
+        .unwrap();
+        // Escaped JSON strings can't be borrowed from the file as they're read.
+        let json = Config::from_slice(
+            br#"{"name": "test", "max-spritesheet-size": [512, 512], "inputs": [{"glob": "assets\/*.png", "packable": true, "alpha-bleed-channels": "r\u0067"}]}"#,
+            Path::new("tarmac.json"),
+        )
+        .unwrap();
+
+        assert_eq!(format!("{:?}", json), format!("{:?}", toml));
+
+        assert!(matches!(
+            Config::from_slice(b"", Path::new("tarmac.yaml")),
+            Err(ConfigError::UnknownFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn spritesheet_name_templates_are_validated() {
+        let validate = |template: &str| {
+            let source = format!(
+                "name = \"test\"\nspritesheet-name-template = \"{}\"\n",
+                template
+            );
+            Config::from_slice(source.as_bytes(), Path::new("tarmac.toml"))
+                .unwrap()
+                .validate()
+        };
+
+        assert!(validate("spritesheet-{index}").is_ok());
+        assert!(validate("sheet_{group}_{index}").is_ok());
+
+        assert!(matches!(
+            validate("sheet_{group}"),
+            Err(ConfigError::NonUniqueSpritesheetNames { .. })
+        ));
+        assert!(matches!(
+            validate("sheet_{name}_{index}"),
+            Err(ConfigError::UnknownTemplatePlaceholder { placeholder, .. }) if placeholder == "name"
+        ));
+        assert!(matches!(
+            validate("sheet_{index"),
+            Err(ConfigError::UnclosedTemplatePlaceholder { .. })
+        ));
+    }
+}
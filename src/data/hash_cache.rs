@@ -0,0 +1,132 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::Metadata,
+    io,
+    path::Path,
+    time::SystemTime,
+};
+
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use crate::{asset_name::AssetName, atomic_write::write_atomic};
+
+static HASH_CACHE_FILENAME: &str = ".tarmac-hash-cache.json";
+
+/// Remembers the hash of each input along with the size and modification time
+/// its file had when it was hashed, so that unchanged files don't need to be
+/// read again on the next sync.
+///
+/// Unlike the manifest, this describes files on disk rather than what has been
+/// uploaded, and it's fine to delete at any time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HashCache {
+    entries: BTreeMap<AssetName, HashCacheEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HashCacheEntry {
+    modified: SystemTime,
+    size: u64,
+    hash: String,
+}
+
+impl HashCacheEntry {
+    /// Describes a file with the given metadata and contents hash. Returns
+    /// None if the platform doesn't track modification times.
+    pub fn new(metadata: &Metadata, hash: String) -> Option<Self> {
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            size: metadata.len(),
+            hash,
+        })
+    }
+
+    fn matches(&self, metadata: &Metadata) -> bool {
+        metadata.len() == self.size && metadata.modified().ok() == Some(self.modified)
+    }
+}
+
+impl HashCache {
+    /// Reads the cache from the given project folder. A missing or unreadable
+    /// cache is treated as empty, since it can always be rebuilt.
+    pub fn read_from_folder<P: AsRef<Path>>(folder_path: P) -> Self {
+        let file_path = folder_path.as_ref().join(HASH_CACHE_FILENAME);
+
+        let contents = match fs::read(&file_path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    log::debug!("Couldn't read hash cache: {}", err);
+                }
+
+                return Self::default();
+            }
+        };
+
+        serde_json::from_slice(&contents).unwrap_or_else(|err| {
+            log::debug!("Ignoring invalid hash cache: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn write_to_folder<P: AsRef<Path>>(&self, folder_path: P) -> io::Result<()> {
+        let file_path = folder_path.as_ref().join(HASH_CACHE_FILENAME);
+
+        let serialized = serde_json::to_vec(self)?;
+
+        write_atomic(&file_path, &serialized)?;
+
+        log::trace!("Saved hash cache to {}", file_path.display());
+
+        Ok(())
+    }
+
+    /// The cached hash of the given input, if its file still has the same size
+    /// and modification time as when it was hashed.
+    pub fn get(&self, name: &AssetName, metadata: &Metadata) -> Option<&str> {
+        self.entries
+            .get(name)
+            .filter(|entry| entry.matches(metadata))
+            .map(|entry| entry.hash.as_str())
+    }
+
+    pub fn insert(&mut self, name: AssetName, entry: HashCacheEntry) {
+        self.entries.insert(name, entry);
+    }
+
+    /// Forget about every input that isn't in the given set.
+    pub fn retain(&mut self, names: &HashSet<AssetName>) {
+        self.entries.retain(|name, _| names.contains(name));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn changed_files_miss_the_cache() {
+        let path = env::temp_dir().join(format!("tarmac-hash-cache-{}", std::process::id()));
+        fs::write(&path, "one").unwrap();
+
+        let name = AssetName::from_paths(&env::temp_dir(), &path);
+        let metadata = fs::metadata(&path).unwrap();
+
+        let mut cache = HashCache::default();
+        cache.insert(
+            name.clone(),
+            HashCacheEntry::new(&metadata, "hash".to_owned()).unwrap(),
+        );
+        assert_eq!(cache.get(&name, &metadata), Some("hash"));
+
+        fs::write(&path, "three").unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        assert_eq!(cache.get(&name, &metadata), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::BTreeMap,
+    convert::TryFrom,
     io,
     path::{Path, PathBuf},
 };
@@ -8,38 +9,120 @@ use fs_err as fs;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::asset_name::AssetName;
+use crate::{asset_name::AssetName, atomic_write::write_atomic};
 
 static MANIFEST_FILENAME: &str = "tarmac-manifest.toml";
 
+/// The version of the manifest format written by this version of Tarmac.
+///
+/// Manifests written before the format was versioned have no `version` field
+/// and are treated as version 0.
+pub const MANIFEST_VERSION: u32 = 1;
+
 /// Tracks the status of all configuration, inputs, and outputs as of the last
 /// sync operation.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
+    /// The version of the format this manifest was read from. Manifests are
+    /// always written in the current format.
+    pub version: u32,
+
     pub inputs: BTreeMap<AssetName, InputManifest>,
 }
 
+impl Default for Manifest {
+    fn default() -> Self {
+        Self::new(BTreeMap::new())
+    }
+}
+
+/// The manifest format used before manifests were versioned, which is the
+/// same as version 1 without the `version` field.
+#[derive(Deserialize)]
+struct ManifestV0 {
+    inputs: BTreeMap<AssetName, InputManifest>,
+}
+
+impl From<ManifestV0> for Manifest {
+    fn from(legacy: ManifestV0) -> Self {
+        Self {
+            version: 0,
+            inputs: legacy.inputs,
+        }
+    }
+}
+
+/// What gets written to disk, which always uses the current version.
+#[derive(Serialize)]
+struct ManifestRef<'a> {
+    version: u32,
+    inputs: &'a BTreeMap<AssetName, InputManifest>,
+}
+
 impl Manifest {
+    pub fn new(inputs: BTreeMap<AssetName, InputManifest>) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            inputs,
+        }
+    }
+
+    /// Whether this manifest was read from an older version of the format.
+    pub fn is_outdated(&self) -> bool {
+        self.version < MANIFEST_VERSION
+    }
+
     pub fn read_from_folder<P: AsRef<Path>>(folder_path: P) -> Result<Self, ManifestError> {
         let folder_path = folder_path.as_ref();
         let file_path = &folder_path.join(MANIFEST_FILENAME);
 
         let contents = fs::read(file_path)?;
-        let config =
-            toml::from_slice(&contents).map_err(|source| ManifestError::DeserializeToml {
-                source,
-                file_path: file_path.to_owned(),
-            })?;
 
-        Ok(config)
+        Self::from_slice(&contents, file_path)
+    }
+
+    /// Parses a manifest of any supported version, upgrading it to the current
+    /// format in memory.
+    fn from_slice(contents: &[u8], file_path: &Path) -> Result<Self, ManifestError> {
+        let deserialize_error = |source| ManifestError::DeserializeToml {
+            source,
+            file_path: file_path.to_owned(),
+        };
+
+        let value: toml::Value = toml::from_slice(contents).map_err(deserialize_error)?;
+        let version = match value.get("version") {
+            Some(version) => version
+                .as_integer()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(|| ManifestError::InvalidVersion {
+                    file_path: file_path.to_owned(),
+                })?,
+            None => 0,
+        };
+
+        match version {
+            0 => {
+                let legacy: ManifestV0 = value.try_into().map_err(deserialize_error)?;
+                Ok(legacy.into())
+            }
+            MANIFEST_VERSION => value.try_into().map_err(deserialize_error),
+            _ => Err(ManifestError::UnsupportedVersion {
+                version,
+                file_path: file_path.to_owned(),
+            }),
+        }
     }
 
     pub fn write_to_folder<P: AsRef<Path>>(&self, folder_path: P) -> Result<(), ManifestError> {
         let folder_path = folder_path.as_ref();
         let file_path = &folder_path.join(MANIFEST_FILENAME);
 
-        let serialized = toml::to_vec(self)?;
-        fs::write(file_path, serialized)?;
+        let serialized = toml::to_vec(&ManifestRef {
+            version: MANIFEST_VERSION,
+            inputs: &self.inputs,
+        })?;
+
+        write_atomic(file_path, &serialized)?;
 
         log::trace!("Saved manifest to {}", file_path.display());
 
@@ -61,24 +144,81 @@ pub struct InputManifest {
     /// the portion of the uploaded image that contains this input.
     pub slice: Option<ImageSlice>,
 
+    /// Whether `slice` was rotated by 90 degrees when it was packed.
+    ///
+    /// This is stored separately from the slice to keep manifests without any
+    /// rotated images unchanged.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub rotated: bool,
+
+    /// The transparent border that was cropped from this input before it was
+    /// packed, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trim: Option<ImageTrim>,
+
     /// Whether the config applied to this input asked for it to be packed into
     /// a spritesheet.
     pub packable: bool,
+
+    /// Whether the config applied to this input marked it as a flipbook frame.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flipbook: bool,
+}
+
+impl InputManifest {
+    /// The slice of the uploaded image containing this input, including
+    /// whether it was rotated or trimmed.
+    pub fn image_slice(&self) -> Option<ImageSlice> {
+        self.slice
+            .map(|slice| slice.with_rotated(self.rotated).with_trim(self.trim))
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct ImageSlice {
     coordinates: ((u32, u32), (u32, u32)),
+
+    /// Whether the image in this slice was rotated by 90 degrees clockwise
+    /// when it was packed. Persisted via `InputManifest::rotated`.
+    #[serde(skip)]
+    rotated: bool,
+
+    /// The transparent border cropped from the image before it was packed.
+    /// Persisted via `InputManifest::trim`.
+    #[serde(skip)]
+    trim: Option<ImageTrim>,
 }
 
 impl ImageSlice {
     pub fn new(min: (u32, u32), max: (u32, u32)) -> Self {
         Self {
             coordinates: (min, max),
+            rotated: false,
+            trim: None,
         }
     }
 
+    pub fn with_rotated(self, rotated: bool) -> Self {
+        Self { rotated, ..self }
+    }
+
+    pub fn rotated(&self) -> bool {
+        self.rotated
+    }
+
+    pub fn with_trim(self, trim: Option<ImageTrim>) -> Self {
+        Self { trim, ..self }
+    }
+
+    pub fn trim(&self) -> Option<ImageTrim> {
+        self.trim
+    }
+
     pub fn min(&self) -> (u32, u32) {
         self.coordinates.0
     }
@@ -95,8 +235,29 @@ impl ImageSlice {
     }
 }
 
+/// Describes the transparent border that was cropped from an image before it
+/// was packed, so that the image's original bounds can be reconstructed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ImageTrim {
+    /// The position of the kept part of the image within the original image.
+    pub offset: (u32, u32),
+
+    /// The width and height of the image before it was cropped.
+    pub original_size: (u32, u32),
+}
+
 #[derive(Debug, Error)]
 pub enum ManifestError {
+    #[error("The manifest at {} has a version that isn't a number", .file_path.display())]
+    InvalidVersion { file_path: PathBuf },
+
+    #[error(
+        "The manifest at {} is version {version}, which is newer than this version of Tarmac supports. Try updating Tarmac.",
+        .file_path.display()
+    )]
+    UnsupportedVersion { version: u32, file_path: PathBuf },
+
     #[error("Error deserializing TOML from path {}", .file_path.display())]
     DeserializeToml {
         file_path: PathBuf,
@@ -124,3 +285,75 @@ impl ManifestError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn legacy_manifests_are_upgraded() {
+        let path = Path::new("tarmac-manifest.toml");
+        let legacy = include_bytes!("fixtures/manifest-v0.toml");
+
+        let manifest = Manifest::from_slice(legacy, path).unwrap();
+        assert!(manifest.is_outdated());
+        assert_eq!(manifest.inputs.len(), 2);
+
+        let rewritten = toml::to_vec(&ManifestRef {
+            version: MANIFEST_VERSION,
+            inputs: &manifest.inputs,
+        })
+        .unwrap();
+
+        let upgraded = Manifest::from_slice(&rewritten, path).unwrap();
+        assert!(!upgraded.is_outdated());
+        assert_eq!(
+            toml::to_string(&upgraded.inputs).unwrap(),
+            toml::to_string(&manifest.inputs).unwrap()
+        );
+    }
+
+    #[test]
+    fn interrupted_writes_keep_the_old_manifest() {
+        let folder =
+            std::env::temp_dir().join(format!("tarmac-test-manifest-write-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let legacy = include_bytes!("fixtures/manifest-v0.toml");
+        let old = Manifest::from_slice(legacy, Path::new("tarmac-manifest.toml")).unwrap();
+        old.write_to_folder(&folder).unwrap();
+
+        // Simulate Tarmac dying after writing the new manifest, but before
+        // renaming it over the old one.
+        crate::atomic_write::write_temp_file(
+            &folder.join(MANIFEST_FILENAME),
+            b"[inputs.\"assets/icon.png\"]\nhash = ",
+        )
+        .unwrap();
+
+        let read = Manifest::read_from_folder(&folder).unwrap();
+        assert_eq!(read.inputs.len(), old.inputs.len());
+
+        // The leftover temporary file shouldn't get in the way of the next
+        // write, either.
+        Manifest::default().write_to_folder(&folder).unwrap();
+        assert!(Manifest::read_from_folder(&folder)
+            .unwrap()
+            .inputs
+            .is_empty());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn newer_manifests_are_rejected() {
+        let contents = format!("version = {}\n[inputs]\n", MANIFEST_VERSION + 1);
+        let result = Manifest::from_slice(contents.as_bytes(), Path::new("tarmac-manifest.toml"));
+
+        assert!(matches!(
+            result,
+            Err(ManifestError::UnsupportedVersion { .. })
+        ));
+    }
+}
@@ -1,7 +1,9 @@
 mod config;
+mod hash_cache;
 mod manifest;
 mod sync;
 
 pub use config::*;
+pub use hash_cache::*;
 pub use manifest::*;
 pub use sync::*;
@@ -11,22 +11,40 @@ use path_slash::PathBufExt;
 pub enum AssetId {
     Id(u64),
     Path(PathBuf),
+
+    /// Stands in for an asset that hasn't been uploaded to Roblox, identified
+    /// by the hash of its contents.
+    Placeholder(String),
+}
+
+/// The URI scheme that paths to local assets use unless configured otherwise.
+pub const DEFAULT_PATH_SCHEME: &str = "rbxasset";
+
+impl AssetId {
+    /// Formats this asset ID as a URI. Paths to local assets are given the
+    /// `path_scheme` scheme, or are left as a bare path if it's empty.
+    pub fn to_uri(&self, path_scheme: &str) -> String {
+        match self {
+            Self::Id(id) => format!("rbxassetid://{}", id),
+            Self::Path(path) => {
+                let path = path
+                    .to_slash()
+                    .expect("error while converting path to slash");
+
+                if path_scheme.is_empty() {
+                    path
+                } else {
+                    format!("{}://{}", path_scheme, path)
+                }
+            }
+            Self::Placeholder(hash) => format!("tarmac-hash://{}", hash),
+        }
+    }
 }
 
 impl fmt::Display for AssetId {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            formatter,
-            "{}",
-            match &self {
-                Self::Id(id) => format!("rbxassetid://{}", id),
-                Self::Path(path) => format!(
-                    "rbxasset://{}",
-                    path.to_slash()
-                        .expect("error while converting path to slash")
-                ),
-            }
-        )
+        write!(formatter, "{}", self.to_uri(DEFAULT_PATH_SCHEME))
     }
 }
 
@@ -52,12 +70,17 @@ pub struct SyncInput {
     /// The configuration that applied to this input when it was discovered.
     pub config: InputConfig,
 
-    /// The contents of the file this input originated from.
-    pub contents: Vec<u8>,
+    /// The contents of the file this input originated from, or None if reading
+    /// the file was skipped because it was unmodified since `--since`.
+    pub contents: Option<Vec<u8>>,
 
     /// A hash of `contents`.
     pub hash: String,
 
+    /// The width and height of this input, if it's an image that we were able
+    /// to read the header of.
+    pub dimensions: Option<(u32, u32)>,
+
     /// If this input has been part of an upload to Roblox.com, contains the
     /// asset ID that contains the data from this input.
     pub id: Option<AssetId>,
@@ -69,7 +92,32 @@ pub struct SyncInput {
 
 impl SyncInput {
     pub fn is_unchanged_since_last_sync(&self, old_manifest: &InputManifest) -> bool {
-        self.hash == old_manifest.hash && self.config.packable == old_manifest.packable
+        self.hash == old_manifest.hash
+            && self.config.packable == old_manifest.packable
+            && self.config.flipbook == old_manifest.flipbook
+    }
+
+    /// The number at the end of this input's file name, like 12 for
+    /// `run_12.png`. Flipbook frames are ordered by this number, so that frame
+    /// 10 comes after frame 9.
+    pub fn frame_number(&self) -> Option<u64> {
+        let file_stem = self.path_without_dpi_scale.file_stem()?.to_str()?;
+        let digit_count = file_stem
+            .chars()
+            .rev()
+            .take_while(char::is_ascii_digit)
+            .count();
+        let digits_start = file_stem.len() - digit_count;
+
+        file_stem[digits_start..].parse().ok()
+    }
+
+    /// The contents of the file this input originated from. Panics if they
+    /// haven't been read yet.
+    pub fn contents(&self) -> &[u8] {
+        self.contents
+            .as_deref()
+            .expect("contents of input were used before being read")
     }
 
     /// Creates a non-unique, human-friendly name to refer to this input.
@@ -88,3 +136,67 @@ impl SyncInput {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::Path;
+
+    fn path_id() -> AssetId {
+        AssetId::Path(["tarmac", "icons", "a.png"].iter().collect())
+    }
+
+    #[test]
+    fn path_uses_rbxasset_by_default() {
+        assert_eq!(path_id().to_string(), "rbxasset://tarmac/icons/a.png");
+        assert_eq!(
+            path_id().to_uri(DEFAULT_PATH_SCHEME),
+            "rbxasset://tarmac/icons/a.png"
+        );
+    }
+
+    #[test]
+    fn path_uses_configured_scheme() {
+        assert_eq!(
+            path_id().to_uri("rbxthumb"),
+            "rbxthumb://tarmac/icons/a.png"
+        );
+    }
+
+    #[test]
+    fn path_without_scheme_is_bare() {
+        assert_eq!(path_id().to_uri(""), "tarmac/icons/a.png");
+    }
+
+    #[test]
+    fn frame_numbers_come_from_the_end_of_file_names() {
+        let frame_number = |path: &str| {
+            let config: InputConfig = toml::from_str("glob = \"*.png\"").unwrap();
+
+            SyncInput {
+                name: AssetName::from_paths(Path::new(""), Path::new(path)),
+                path: path.into(),
+                path_without_dpi_scale: path.into(),
+                dpi_scale: 1,
+                config,
+                contents: None,
+                hash: String::new(),
+                dimensions: None,
+                id: None,
+                slice: None,
+            }
+            .frame_number()
+        };
+
+        assert_eq!(frame_number("run_12.png"), Some(12));
+        assert_eq!(frame_number("run2_009.png"), Some(9));
+        assert_eq!(frame_number("7.png"), Some(7));
+        assert_eq!(frame_number("idle.png"), None);
+    }
+
+    #[test]
+    fn ids_ignore_path_scheme() {
+        assert_eq!(AssetId::Id(5).to_uri(""), "rbxassetid://5");
+    }
+}
@@ -0,0 +1,494 @@
+//! Decodes input images, and encodes them into the PNG files that Tarmac
+//! uploads.
+//!
+//! Only PNG support is built by default. Other formats are enabled with cargo
+//! features, which keeps minimal builds of Tarmac smaller.
+//!
+//! Roblox ignores EXIF orientation, and re-encoding images as PNG drops it, so
+//! images that carry it are rotated into place while they're decoded.
+//!
+//! Decoded images are usually RGBA, even when their source file was grayscale
+//! or had no transparency. Encoding them as-is can produce files several times
+//! larger than they need to be, so we pick the smallest color type that can
+//! represent every pixel exactly. Images with few enough colors can also be
+//! encoded with a palette, which is usually much smaller for flat UI art.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Seek},
+    path::Path,
+};
+
+use image::{
+    codecs::png::PngEncoder, ColorType, DynamicImage, GenericImageView, ImageError, ImageFormat,
+    ImageResult,
+};
+use thiserror::Error;
+
+/// Decodes an image, telling the user how to enable its format if support for
+/// it wasn't built into this copy of Tarmac.
+///
+/// Some formats, like TGA, have no signature to recognize them by, so the
+/// image's path is used as a fallback to tell which format it's in.
+///
+/// If `apply_exif_orientation` is set, the image is also rotated and flipped
+/// the way its EXIF orientation says it should be displayed.
+pub(crate) fn decode_image(
+    data: &[u8],
+    path: &Path,
+    apply_exif_orientation: bool,
+) -> Result<DynamicImage, DecodeError> {
+    let format = image_format(data, path);
+
+    if let Some(format) = format {
+        if let Some(feature) = missing_feature(format) {
+            return Err(DecodeError::FormatNotEnabled { format, feature });
+        }
+    }
+
+    let img = match format {
+        Some(format) => image::load_from_memory_with_format(data, format)?,
+        None => image::load_from_memory(data)?,
+    };
+
+    if format == Some(ImageFormat::Ico) {
+        let entries = ico_entry_count(data);
+        if entries > 1 {
+            log::warn!(
+                "{} contains {} images, only the largest one will be used",
+                path.display(),
+                entries
+            );
+        }
+    }
+
+    if apply_exif_orientation {
+        if let Some(orientation) = read_exif_orientation(&mut io::Cursor::new(data)) {
+            return Ok(apply_orientation(img, orientation));
+        }
+    }
+
+    Ok(img)
+}
+
+/// Works out which format an image is in, first from its contents and then
+/// from its file extension.
+pub(crate) fn image_format(data: &[u8], path: &Path) -> Option<ImageFormat> {
+    image::guess_format(data)
+        .or_else(|_| ImageFormat::from_path(path))
+        .ok()
+}
+
+/// The number of images stored in an ICO file, read from its header.
+fn ico_entry_count(data: &[u8]) -> u16 {
+    match data.get(4..6) {
+        Some(count) => u16::from_le_bytes([count[0], count[1]]),
+        None => 0,
+    }
+}
+
+/// Reads the EXIF orientation of an image, a number from 1 to 8. Returns None
+/// if the image has no orientation, or if it's the default of 1.
+pub(crate) fn read_exif_orientation<R: BufRead + Seek>(reader: &mut R) -> Option<u32> {
+    let exif = exif::Reader::new().read_from_container(reader).ok()?;
+    let field = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?;
+
+    match field.value.get_uint(0)? {
+        orientation @ 2..=8 => Some(orientation),
+        _ => None,
+    }
+}
+
+/// Whether an image with the given EXIF orientation is stored on its side, with
+/// its width and height swapped.
+pub(crate) fn orientation_swaps_dimensions(orientation: u32) -> bool {
+    orientation >= 5
+}
+
+/// Transforms an image stored with the given EXIF orientation into the way it
+/// should be displayed.
+fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// The cargo feature that needs to be enabled to decode the given format, if
+/// it isn't enabled already.
+fn missing_feature(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Jpeg if !cfg!(feature = "jpeg") => Some("jpeg"),
+        ImageFormat::WebP if !cfg!(feature = "webp") => Some("webp"),
+        ImageFormat::Ico if !cfg!(feature = "ico") => Some("ico"),
+        ImageFormat::Tga if !cfg!(feature = "tga") => Some("tga"),
+        ImageFormat::Gif if !cfg!(feature = "gif") => Some("gif"),
+        ImageFormat::Bmp if !cfg!(feature = "bmp") => Some("bmp"),
+        ImageFormat::Tiff if !cfg!(feature = "tiff") => Some("tiff"),
+        ImageFormat::Pnm if !cfg!(feature = "pnm") => Some("pnm"),
+        ImageFormat::Hdr if !cfg!(feature = "hdr") => Some("hdr"),
+        ImageFormat::Dds if !cfg!(feature = "dds") => Some("dds"),
+        ImageFormat::Farbfeld if !cfg!(feature = "farbfeld") => Some("farbfeld"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("{format:?} images aren't supported by this build of Tarmac. Rebuild it with the '{feature}' feature to use them.")]
+    FormatNotEnabled {
+        format: ImageFormat,
+        feature: &'static str,
+    },
+
+    #[error(transparent)]
+    Image {
+        #[from]
+        source: ImageError,
+    },
+}
+
+/// Encodes the image as a PNG using the smallest color type that loses no
+/// information.
+///
+/// If `indexed` is set and the image has at most 256 distinct colors, it's
+/// encoded with a palette instead. Images with more colors fall back to being
+/// encoded in full color.
+pub(crate) fn encode_png(img: &DynamicImage, indexed: bool) -> ImageResult<Vec<u8>> {
+    let (width, height) = img.dimensions();
+
+    if indexed {
+        if let Some(encoded_image) = encode_indexed_png(img) {
+            return Ok(encoded_image);
+        }
+    }
+
+    let color_type = minimal_color_type(img);
+
+    let bytes = match color_type {
+        ColorType::L8 => img.to_luma8().into_raw(),
+        ColorType::La8 => img.to_luma_alpha8().into_raw(),
+        ColorType::Rgb8 => img.to_rgb8().into_raw(),
+        ColorType::Rgba8 => img.to_rgba8().into_raw(),
+        _ => img.to_bytes(),
+    };
+
+    let mut encoded_image: Vec<u8> = Vec::new();
+    PngEncoder::new(&mut encoded_image).encode(&bytes, width, height, color_type)?;
+
+    Ok(encoded_image)
+}
+
+/// Encodes the image as an 8-bit indexed PNG, with a tRNS chunk holding the
+/// palette's alpha values if any of them aren't opaque. Returns None if the
+/// image has too many colors to fit in a palette.
+fn encode_indexed_png(img: &DynamicImage) -> Option<Vec<u8>> {
+    let (width, height) = img.dimensions();
+    let rgba = img.to_rgba8();
+
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut indices = HashMap::new();
+    let mut data = Vec::with_capacity(rgba.len() / 4);
+
+    for pixel in rgba.pixels() {
+        let index = match indices.get(&pixel.0) {
+            Some(&index) => index,
+            None => {
+                if palette.len() == 256 {
+                    return None;
+                }
+
+                let index = palette.len() as u8;
+                palette.push(pixel.0);
+                indices.insert(pixel.0, index);
+                index
+            }
+        };
+
+        data.push(index);
+    }
+
+    let colors: Vec<u8> = palette
+        .iter()
+        .flat_map(|color| &color[..3])
+        .copied()
+        .collect();
+
+    // Trailing opaque entries can be left out of tRNS, but sorting the palette
+    // to take advantage of that isn't worth the trouble.
+    let alphas: Vec<u8> = palette.iter().map(|color| color[3]).collect();
+    let has_transparency = alphas.iter().any(|&alpha| alpha != 255);
+
+    let mut encoded_image = Vec::new();
+    let mut encoder = png::Encoder::new(&mut encoded_image, width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_chunk(*b"PLTE", &colors).ok()?;
+    if has_transparency {
+        writer.write_chunk(*b"tRNS", &alphas).ok()?;
+    }
+    writer.write_image_data(&data).ok()?;
+    drop(writer);
+
+    Some(encoded_image)
+}
+
+/// Finds the smallest 8-bit color type that can store every pixel of the
+/// image. Images with other bit depths keep their own color type.
+///
+/// Transparent pixels count too, since alpha bleeding stores colors in them
+/// that need to survive encoding.
+fn minimal_color_type(img: &DynamicImage) -> ColorType {
+    match img.color() {
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => {}
+        other => return other,
+    }
+
+    let mut grayscale = true;
+    let mut opaque = true;
+
+    for (_, _, pixel) in img.pixels() {
+        grayscale &= pixel[0] == pixel[1] && pixel[1] == pixel[2];
+        opaque &= pixel[3] == 255;
+
+        if !grayscale && !opaque {
+            break;
+        }
+    }
+
+    match (grayscale, opaque) {
+        (true, true) => ColorType::L8,
+        (true, false) => ColorType::La8,
+        (false, true) => ColorType::Rgb8,
+        (false, false) => ColorType::Rgba8,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn grayscale_with_alpha_stays_grayscale() {
+        let mut img = RgbaImage::from_pixel(2, 2, Rgba([50, 50, 50, 255]));
+        img.put_pixel(1, 1, Rgba([50, 50, 50, 0]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        assert_eq!(minimal_color_type(&img), ColorType::La8);
+
+        let decoded = image::load_from_memory(&encode_png(&img, false).unwrap()).unwrap();
+        assert_eq!(decoded.color(), ColorType::La8);
+        assert_eq!(decoded.to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn low_color_images_can_be_indexed() {
+        let mut img = RgbaImage::from_pixel(16, 16, Rgba([20, 120, 220, 255]));
+        img.put_pixel(0, 0, Rgba([200, 10, 10, 0]));
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 128]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        let encoded = encode_png(&img, true).unwrap();
+        let decoder = png::Decoder::new(encoded.as_slice());
+        let (_, reader) = decoder.read_info().unwrap();
+        assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+
+        let decoded = image::load_from_memory(&encoded).unwrap();
+        assert_eq!(decoded.to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn images_with_many_colors_are_not_indexed() {
+        let img = RgbaImage::from_fn(32, 32, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        let encoded = encode_png(&img, true).unwrap();
+        let decoder = png::Decoder::new(encoded.as_slice());
+        let (_, reader) = decoder.read_info().unwrap();
+        assert_eq!(reader.info().color_type, png::ColorType::RGB);
+    }
+
+    #[test]
+    fn bled_colors_keep_rgba() {
+        // The transparent pixel holds a bled color, which a grayscale encoding
+        // would throw away.
+        let mut img = RgbaImage::from_pixel(2, 1, Rgba([50, 50, 50, 255]));
+        img.put_pixel(1, 0, Rgba([200, 10, 10, 0]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        assert_eq!(minimal_color_type(&img), ColorType::Rgba8);
+    }
+
+    #[cfg(not(feature = "webp"))]
+    #[test]
+    fn webp_needs_feature() {
+        let data = b"RIFF\0\0\0\0WEBPVP8 ";
+
+        assert!(matches!(
+            decode_image(data, Path::new("image.webp"), true),
+            Err(DecodeError::FormatNotEnabled {
+                format: ImageFormat::WebP,
+                feature: "webp",
+            })
+        ));
+    }
+
+    #[cfg(not(feature = "gif"))]
+    #[test]
+    fn gif_needs_feature() {
+        assert!(matches!(
+            decode_image(b"GIF89a", Path::new("image.gif"), true),
+            Err(DecodeError::FormatNotEnabled {
+                format: ImageFormat::Gif,
+                feature: "gif",
+            })
+        ));
+    }
+
+    #[cfg(not(feature = "tga"))]
+    #[test]
+    fn tga_is_recognized_by_its_extension() {
+        // TGA files have no signature, so only the extension gives them away.
+        let data = [0u8; 18];
+
+        assert!(matches!(
+            decode_image(&data, Path::new("image.tga"), true),
+            Err(DecodeError::FormatNotEnabled {
+                format: ImageFormat::Tga,
+                feature: "tga",
+            })
+        ));
+    }
+
+    #[test]
+    fn ico_entries_are_counted() {
+        assert_eq!(ico_entry_count(&[0, 0, 1, 0, 3, 0]), 3);
+        assert_eq!(ico_entry_count(&[0, 0, 1]), 0);
+    }
+
+    /// Builds an image whose pixels are numbered in reading order, so that any
+    /// transformation of it can be told apart.
+    fn numbered_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(image::GrayImage::from_fn(width, height, |x, y| {
+            image::Luma([(y * width + x) as u8])
+        }))
+    }
+
+    fn pixel_rows(img: &DynamicImage) -> Vec<Vec<u8>> {
+        let img = img.to_luma8();
+        img.rows()
+            .map(|row| row.map(|pixel| pixel.0[0]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn every_orientation_is_applied() {
+        // The stored pixels are:
+        //   0 1 2
+        //   3 4 5
+        let expected: [(u32, &[&[u8]]); 8] = [
+            (1, &[&[0, 1, 2], &[3, 4, 5]]),
+            (2, &[&[2, 1, 0], &[5, 4, 3]]),
+            (3, &[&[5, 4, 3], &[2, 1, 0]]),
+            (4, &[&[3, 4, 5], &[0, 1, 2]]),
+            (5, &[&[0, 3], &[1, 4], &[2, 5]]),
+            (6, &[&[3, 0], &[4, 1], &[5, 2]]),
+            (7, &[&[5, 2], &[4, 1], &[3, 0]]),
+            (8, &[&[2, 5], &[1, 4], &[0, 3]]),
+        ];
+
+        for (orientation, rows) in expected.iter() {
+            let oriented = apply_orientation(numbered_image(3, 2), *orientation);
+
+            assert_eq!(pixel_rows(&oriented), *rows, "orientation {}", orientation);
+            assert_eq!(
+                oriented.width() == 2,
+                orientation_swaps_dimensions(*orientation),
+                "orientation {}",
+                orientation
+            );
+        }
+    }
+
+    /// Builds a PNG with an eXIf chunk holding the given orientation.
+    fn png_with_orientation(orientation: u16) -> Vec<u8> {
+        let png = encode_png(&numbered_image(3, 2), false).unwrap();
+
+        // A little-endian TIFF header, followed by an IFD with one entry.
+        let mut exif = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0".to_vec();
+        exif.extend_from_slice(&orientation.to_le_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+
+        let mut chunk = b"eXIf".to_vec();
+        chunk.extend_from_slice(&exif);
+        let crc = crc32(&chunk);
+
+        // The eXIf chunk goes right after the 8 byte signature and the
+        // 25 byte IHDR chunk.
+        let mut data = png[..33].to_vec();
+        data.extend_from_slice(&(exif.len() as u32).to_be_bytes());
+        data.extend_from_slice(&chunk);
+        data.extend_from_slice(&crc.to_be_bytes());
+        data.extend_from_slice(&png[33..]);
+        data
+    }
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in data {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    #[test]
+    fn orientation_is_read_from_exif() {
+        for orientation in 1..=8 {
+            let data = png_with_orientation(orientation);
+            let read = read_exif_orientation(&mut io::Cursor::new(&data));
+
+            let expected = if orientation == 1 {
+                None
+            } else {
+                Some(u32::from(orientation))
+            };
+            assert_eq!(read, expected, "orientation {}", orientation);
+
+            let decoded = decode_image(&data, Path::new("image.png"), true).unwrap();
+            assert_eq!(
+                pixel_rows(&decoded),
+                pixel_rows(&apply_orientation(
+                    numbered_image(3, 2),
+                    u32::from(orientation)
+                ))
+            );
+
+            let ignored = decode_image(&data, Path::new("image.png"), false).unwrap();
+            assert_eq!(pixel_rows(&ignored), pixel_rows(&numbered_image(3, 2)));
+        }
+    }
+
+    #[test]
+    fn opaque_color_drops_alpha() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([1, 2, 3, 255])));
+
+        assert_eq!(minimal_color_type(&img), ColorType::Rgb8);
+    }
+}
@@ -0,0 +1,73 @@
+//! Formatting for `--log-format json`, which writes each log event as one
+//! JSON object per line.
+
+use std::time::SystemTime;
+
+use log::kv::{self, VisitSource};
+use serde_json::{Map, Value};
+
+/// Builds the JSON object for a log event. Context attached to the event, like
+/// the name of the asset it's about in `log::warn!(asset:% = name; "...")`,
+/// becomes extra fields next to the standard ones.
+pub fn event_to_json(record: &log::Record<'_>, timestamp: SystemTime) -> Value {
+    let mut event = Map::new();
+    event.insert(
+        "timestamp".to_owned(),
+        humantime::format_rfc3339_millis(timestamp)
+            .to_string()
+            .into(),
+    );
+    event.insert("level".to_owned(), record.level().to_string().into());
+    event.insert("target".to_owned(), record.target().into());
+    event.insert("message".to_owned(), record.args().to_string().into());
+
+    let mut context = ContextVisitor(&mut event);
+    let _ = record.key_values().visit(&mut context);
+
+    Value::Object(event)
+}
+
+struct ContextVisitor<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for ContextVisitor<'_> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        // Standard fields can't be replaced by context.
+        self.0
+            .entry(key.as_str().to_owned())
+            .or_insert_with(|| value.to_string().into());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn context_becomes_fields() {
+        let context = [("asset", "assets/icon.png"), ("level", "ignored")];
+        let event = event_to_json(
+            &log::Record::builder()
+                .args(format_args!("Skipping {}", "assets/icon.png"))
+                .level(log::Level::Warn)
+                .target("tarmac::commands::sync")
+                .key_values(&context)
+                .build(),
+            UNIX_EPOCH + Duration::from_millis(1500),
+        );
+
+        assert_eq!(
+            event,
+            serde_json::json!({
+                "timestamp": "1970-01-01T00:00:01.500Z",
+                "level": "WARN",
+                "target": "tarmac::commands::sync",
+                "message": "Skipping assets/icon.png",
+                "asset": "assets/icon.png",
+            })
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! Tarmac is a resource compiler and asset manager for Roblox projects.
+//!
+//! The `tarmac` command line tool is a thin wrapper over this library. To sync
+//! a project from another tool, open a [`SyncSession`] and sync it with any
+//! [`SyncBackend`](sync_backend::SyncBackend):
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use tarmac::{sync_backend::DebugSyncBackend, SyncSession, SyncSettings};
+//!
+//! let mut session = SyncSession::open(Path::new("assets"), &SyncSettings::default())?;
+//! let report = session.sync(&mut DebugSyncBackend::new())?;
+//!
+//! println!("Uploaded {} inputs", report.uploaded.len());
+//! # Ok::<(), tarmac::SyncError>(())
+//! ```
+
+pub mod alpha_bleed;
+pub mod asset_name;
+mod atlas;
+mod atomic_write;
+mod auth_cookie;
+mod codegen;
+pub mod commands;
+pub mod data;
+mod dpi_scale;
+pub mod glob;
+mod image_encoding;
+pub mod json_log;
+mod lua_ast;
+pub mod options;
+mod project_lock;
+pub mod roblox_web_api;
+pub mod sync_backend;
+
+pub use commands::{SyncError, SyncReport, SyncSession, SyncSettings, UploadErrorPolicy};
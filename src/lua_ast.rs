@@ -240,7 +240,7 @@ fn is_valid_ident_char(value: char) -> bool {
 }
 
 /// Tells whether the given string is a valid Lua identifier.
-fn is_valid_ident(value: &str) -> bool {
+pub(crate) fn is_valid_ident(value: &str) -> bool {
     let mut chars = value.chars();
 
     match chars.next() {
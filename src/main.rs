@@ -1,33 +1,30 @@
-mod alpha_bleed;
-mod asset_name;
-mod auth_cookie;
-mod codegen;
-mod commands;
-mod data;
-mod dpi_scale;
-mod glob;
-mod lua_ast;
-mod options;
-mod roblox_web_api;
-mod sync_backend;
-
-use std::{env, panic, process};
+use std::{env, io::Write, panic, process, time::SystemTime};
 
 use backtrace::Backtrace;
 use structopt::StructOpt;
 
-use crate::options::{Options, Subcommand};
+use tarmac::{
+    commands, json_log,
+    options::{LogFormat, Options, Subcommand},
+    roblox_web_api::HTTP_LOG_TARGET,
+};
 
 fn run(options: Options) -> Result<(), anyhow::Error> {
     match options.command {
         Subcommand::UploadImage(upload_options) => {
-            commands::upload_image(options.global, upload_options)
+            commands::upload_image(options.global, upload_options)?
         }
         Subcommand::Sync(sync_options) => commands::sync(options.global, sync_options)?,
         Subcommand::CreateCacheMap(sub_options) => {
             commands::create_cache_map(options.global, sub_options)?
         }
         Subcommand::AssetList(sub_options) => commands::asset_list(options.global, sub_options)?,
+        Subcommand::Init(sub_options) => commands::init(options.global, sub_options)?,
+        Subcommand::Whoami => commands::whoami(options.global)?,
+        Subcommand::Codegen(sub_options) => commands::codegen(options.global, sub_options)?,
+        Subcommand::Migrate(sub_options) => commands::migrate(options.global, sub_options)?,
+        Subcommand::Backup(sub_options) => commands::backup(options.global, sub_options)?,
+        Subcommand::Doctor(sub_options) => commands::doctor(options.global, sub_options)?,
     }
 
     Ok(())
@@ -83,20 +80,47 @@ fn main() {
     let options = Options::from_args();
 
     let log_filter = match options.global.verbosity {
+        0 if options.global.quiet => "error",
+        1 if options.global.quiet => "error,tarmac=debug",
+        2 if options.global.quiet => "error,tarmac=trace",
         0 => "info",
         1 => "info,tarmac=debug",
         2 => "info,tarmac=trace",
         _ => "trace",
     };
 
+    let log_filter = if options.global.verbose_http {
+        format!("{},{}=debug", log_filter, HTTP_LOG_TARGET)
+    } else {
+        log_filter.to_owned()
+    };
+
     let log_env = env_logger::Env::default().default_filter_or(log_filter);
 
-    env_logger::Builder::from_env(log_env)
-        .format_module_path(false)
-        .format_timestamp(None)
-        // Indent following lines equal to the log level label, like `[ERROR] `
-        .format_indent(Some(8))
-        .init();
+    let mut builder = env_logger::Builder::from_env(log_env);
+
+    match options.global.log_format {
+        LogFormat::Human => {
+            builder
+                .format_module_path(false)
+                .format_timestamp(None)
+                // Indent following lines equal to the log level label, like `[ERROR] `
+                .format_indent(Some(8));
+        }
+        LogFormat::Json => {
+            // One object per line, so that log aggregators can ingest each
+            // event on its own.
+            builder.format(|buf, record| {
+                writeln!(
+                    buf,
+                    "{}",
+                    json_log::event_to_json(record, SystemTime::now())
+                )
+            });
+        }
+    }
+
+    builder.init();
 
     if let Err(err) = run(options) {
         log::error!("{:?}", err);
@@ -1,8 +1,15 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
+use reqwest::Url;
 use secrecy::SecretString;
 use structopt::StructOpt;
 
+use crate::{auth_cookie::get_auth_cookie, glob::Glob, roblox_web_api::ClientSettings};
+
 #[derive(Debug, StructOpt)]
 #[structopt(about = env!("CARGO_PKG_DESCRIPTION"))]
 pub struct Options {
@@ -21,9 +28,162 @@ pub struct GlobalOptions {
     #[structopt(long, global(true))]
     pub auth: Option<SecretString>,
 
+    /// A path to a file containing the authentication cookie for Tarmac to
+    /// use. Unlike --auth, this keeps the cookie out of shell history and
+    /// process listings. Trailing whitespace in the file is ignored. --auth
+    /// takes precedence over this.
+    #[structopt(
+        long,
+        global(true),
+        parse(try_from_str = read_secret_file)
+    )]
+    pub auth_file: Option<SecretString>,
+
     /// Sets verbosity level. Can be specified multiple times.
     #[structopt(long = "verbose", short, global(true), parse(from_occurrences))]
     pub verbosity: u8,
+
+    /// Only print errors to stderr. Output meant for other programs, like the
+    /// ID printed by upload-image, is still written to stdout. Passing
+    /// --verbose as well turns Tarmac's own debug messages back on.
+    #[structopt(long, short, global(true))]
+    pub quiet: bool,
+
+    /// Log the method, URL, status, and duration of every request made to a
+    /// Roblox API. Credentials in URLs are never logged.
+    #[structopt(long, global(true))]
+    pub verbose_http: bool,
+
+    /// The format to write log messages in.
+    ///
+    /// Options:
+    ///
+    /// - human: Readable text
+    ///
+    /// - json: One JSON object per line, with timestamp, level, target, and
+    ///   message fields
+    #[structopt(long, global(true), default_value = "human")]
+    pub log_format: LogFormat,
+
+    /// Overrides the scheme and host of every Roblox API that Tarmac talks to,
+    /// like `http://localhost:8080`. Intended for testing against a mock server
+    /// or going through a proxy.
+    #[structopt(
+        long,
+        global(true),
+        env = "TARMAC_API_BASE_URL",
+        parse(try_from_str = parse_base_url)
+    )]
+    pub api_base_url: Option<Url>,
+
+    /// The number of seconds to wait for a request to a Roblox API to finish
+    /// before giving up on it.
+    #[structopt(long, global(true), default_value = "30")]
+    pub request_timeout: u64,
+
+    /// The number of seconds to wait for a connection to a Roblox API to be
+    /// established before giving up on it.
+    #[structopt(long, global(true), default_value = "10")]
+    pub connect_timeout: u64,
+
+    /// A header to attach to every request Tarmac makes, formatted like
+    /// `Name: value`. Usually needed to authenticate with a proxy, which can be
+    /// set with the HTTPS_PROXY and HTTP_PROXY environment variables.
+    #[structopt(
+        long,
+        global(true),
+        env = "TARMAC_EXTRA_HEADER",
+        hide_env_values = true
+    )]
+    pub extra_header: Option<SecretString>,
+}
+
+impl GlobalOptions {
+    /// The authentication cookie given on the command line, either directly
+    /// or through a file.
+    pub fn auth(&self) -> Option<SecretString> {
+        self.command_line_credentials().map(|(auth, _)| auth)
+    }
+
+    /// The authentication cookie Tarmac should use, along with a description
+    /// of where it came from. Cookies given on the command line are used
+    /// first, then the one Roblox Studio saved when the user logged in.
+    pub fn credentials(&self) -> Option<(SecretString, &'static str)> {
+        self.command_line_credentials()
+            .or_else(|| get_auth_cookie().map(|auth| (auth, "Roblox Studio's saved login")))
+    }
+
+    fn command_line_credentials(&self) -> Option<(SecretString, &'static str)> {
+        match (&self.auth, &self.auth_file) {
+            (Some(auth), _) => Some((auth.clone(), "--auth")),
+            (None, Some(auth)) => Some((auth.clone(), "--auth-file")),
+            (None, None) => None,
+        }
+    }
+
+    /// The settings to create HTTP clients with.
+    pub fn client_settings(&self) -> ClientSettings {
+        ClientSettings {
+            base_url: self.api_base_url.clone(),
+            request_timeout: Duration::from_secs(self.request_timeout),
+            connect_timeout: Duration::from_secs(self.connect_timeout),
+            extra_header: self.extra_header.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<LogFormat, Self::Err> {
+        match value {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+
+            _ => Err(String::from(
+                "Invalid log format. Valid options are human and json.",
+            )),
+        }
+    }
+}
+
+fn parse_base_url(value: &str) -> Result<Url, String> {
+    let url = Url::parse(value).map_err(|err| format!("Invalid API base URL: {}", err))?;
+
+    match url.scheme() {
+        "http" | "https" if !url.cannot_be_a_base() => Ok(url),
+        _ => Err(format!(
+            "Invalid API base URL: {} is not an http or https URL.",
+            value
+        )),
+    }
+}
+
+fn read_secret_file(path: &str) -> Result<SecretString, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+
+    Ok(SecretString::new(contents.trim_end().to_owned()))
+}
+
+fn parse_request_limit(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(limit) if limit > 0 => Ok(limit),
+        _ => Err(format!("{} is not a positive number of requests.", value)),
+    }
+}
+
+fn parse_percentage(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(percentage) if percentage <= 100 => Ok(percentage),
+        _ => Err(format!("{} is not a percentage from 0 to 100.", value)),
+    }
 }
 
 #[derive(Debug, StructOpt)]
@@ -41,11 +201,37 @@ pub enum Subcommand {
 
     /// Creates a file that lists all assets required by the project.
     AssetList(AssetListOptions),
+
+    /// Creates a starter Tarmac project with a tarmac.toml file, an assets
+    /// folder, and a src folder for generated code.
+    Init(InitOptions),
+
+    /// Prints which Roblox account Tarmac is logged in as, and where its
+    /// credentials came from.
+    Whoami,
+
+    /// Generates code for your Tarmac project from the asset IDs in its
+    /// manifest, without syncing or using the network. Only the generated
+    /// code is written.
+    Codegen(CodegenCommandOptions),
+
+    /// Upgrades your Tarmac project's manifest to the format used by this
+    /// version of Tarmac, without syncing.
+    Migrate(MigrateOptions),
+
+    /// Downloads every asset referenced by your Tarmac project's manifest into
+    /// a folder that mirrors the project's inputs.
+    Backup(BackupOptions),
+
+    /// Checks that Tarmac can find credentials, write to the local content
+    /// folder, decode images, and sync to the debug target, without uploading
+    /// anything to Roblox.
+    Doctor(DoctorOptions),
 }
 
 #[derive(Debug, StructOpt)]
 pub struct UploadImageOptions {
-    /// The path to the image to upload.
+    /// The path to the image to upload, or `-` to read it from stdin.
     pub path: PathBuf,
 
     /// The name to give to the resulting Decal asset.
@@ -55,6 +241,16 @@ pub struct UploadImageOptions {
     /// The description to give to the resulting Decal asset.
     #[structopt(long, default_value = "Uploaded by Tarmac.")]
     pub description: String,
+
+    /// Upload the image as its pixels are stored, ignoring its EXIF
+    /// orientation.
+    #[structopt(long)]
+    pub no_exif_rotate: bool,
+
+    /// Upload the image as an indexed PNG if it has at most 256 colors, which
+    /// is much smaller for flat art like icons.
+    #[structopt(long)]
+    pub encode_indexed: bool,
 }
 
 #[derive(Debug, StructOpt)]
@@ -71,44 +267,230 @@ pub struct SyncOptions {
     /// - debug: Copy to local debug directory for debugging output
     ///
     /// - local: Copy to locally installed Roblox content folder.
+    ///
+    /// - cdn: Write assets to a folder named after their hashes and list them
+    ///   in tarmac-cdn.json, for uploading with another tool.
     #[structopt(long)]
     pub target: SyncTarget,
 
     /// When provided, Tarmac will upload again at most the given number of times
-    /// when it encounters rate limitation errors.
+    /// when it encounters rate limitation or network errors.
     #[structopt(long)]
     pub retry: Option<usize>,
 
+    /// The most retries Tarmac should make across every upload in the sync.
+    /// Once they've been used up, uploads aren't retried anymore, so a sync
+    /// that keeps being rate limited gives up instead of running for hours.
+    #[structopt(long, requires = "retry")]
+    pub max_total_retries: Option<usize>,
+
+    /// Also retry uploads that fail because Roblox responds with a server
+    /// error (5xx). Some uploads are rejected with a server error every time,
+    /// so these aren't retried by default.
+    #[structopt(long, requires = "retry")]
+    pub retry_server_errors: bool,
+
     /// The number of seconds to wait between each re-upload attempts.
     #[structopt(long, default_value = "60")]
     pub retry_delay: u64,
 
-    /// The path to a Tarmac config, or a folder containing a Tarmac project.
-    pub config_path: Option<PathBuf>,
+    /// The percentage by which each wait between re-upload attempts may
+    /// randomly be shortened or lengthened, from 0 to 100. Useful when many
+    /// Tarmac processes share a rate limit, so that they don't all retry at
+    /// once.
+    #[structopt(long, default_value = "0", parse(try_from_str = parse_percentage))]
+    pub retry_jitter: u8,
+
+    /// The maximum number of uploads Tarmac should start per minute. Uploads
+    /// are spaced out evenly to stay under this limit, which avoids being rate
+    /// limited in the first place.
+    #[structopt(long, parse(try_from_str = parse_request_limit))]
+    pub max_requests_per_minute: Option<u32>,
+
+    /// Stop uploading as soon as any upload fails. By default, Tarmac only
+    /// stops early when it's rate limited.
+    #[structopt(long, conflicts_with = "continue-on-error")]
+    pub fail_fast: bool,
+
+    /// Keep uploading after failed uploads, even when rate limited. Successful
+    /// uploads are still recorded in the manifest, and failures are reported
+    /// at the end.
+    #[structopt(long)]
+    pub continue_on_error: bool,
+
+    /// Read and hash every input, instead of trusting the hashes Tarmac saved
+    /// for files whose size and modification time haven't changed.
+    #[structopt(long)]
+    pub no_hash_cache: bool,
+
+    /// The number of threads to use when reading, decoding, and alpha-bleeding
+    /// images. Defaults to the number of logical CPUs.
+    #[structopt(long)]
+    pub jobs: Option<usize>,
+
+    /// Skip reading and hashing inputs whose files were last modified before
+    /// the given RFC 3339 timestamp, like `2024-01-31T12:00:00Z`, and assume
+    /// that they match the manifest instead.
+    ///
+    /// This makes syncing large projects faster, but changes to files whose
+    /// modification time is older than the timestamp will be missed.
+    #[structopt(long, parse(try_from_str = humantime::parse_rfc3339_weak))]
+    pub since: Option<SystemTime>,
+
+    /// Only upload inputs whose path, relative to the project, matches the
+    /// given glob. Can be given more than once to upload inputs matching any
+    /// of the globs. Other inputs keep their entries in the manifest.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = Glob::new))]
+    pub only: Vec<Glob>,
+
+    /// Upload every input again, even if the manifest says it's unchanged.
+    /// Combine with --only to upload just some inputs again. Uploads count
+    /// against your account's upload limits as usual.
+    #[structopt(long)]
+    pub force: bool,
+
+    /// The content folder that the local target writes assets into. Defaults
+    /// to the content folder of the installed copy of Roblox Studio.
+    #[structopt(long)]
+    pub content_path: Option<PathBuf>,
+
+    /// Write a JSON summary of the sync to the given path, with the number of
+    /// inputs uploaded, skipped, and failed, the number of bytes uploaded, and
+    /// how long the sync took.
+    #[structopt(long)]
+    pub summary: Option<PathBuf>,
+
+    /// Instead of writing generated code for inputs with a `codegen-path` to a
+    /// single file, write one file per top-level folder into a folder named
+    /// after `codegen-path` without its extension.
+    #[structopt(long)]
+    pub codegen_split_root: bool,
+
+    /// Don't generate code after syncing, even if the project's config asks
+    /// for it.
+    #[structopt(long)]
+    pub no_codegen: bool,
+
+    /// Text to append to the description of every asset uploaded to Roblox,
+    /// like a build number.
+    #[structopt(long, conflicts_with = "description-git-sha")]
+    pub description_suffix: Option<String>,
+
+    /// Append the current git commit's SHA to the description of every asset
+    /// uploaded to Roblox. Nothing is appended if the project isn't in a git
+    /// repository.
+    #[structopt(long)]
+    pub description_git_sha: bool,
+
+    /// The largest upload Tarmac should attempt, in bytes. Larger images and
+    /// spritesheets are skipped with a warning. Defaults to 20 MiB, the
+    /// largest image Roblox accepts.
+    #[structopt(long)]
+    pub max_upload_size: Option<u64>,
+
+    /// Fail uploads that are larger than --max-upload-size instead of skipping
+    /// them.
+    #[structopt(long)]
+    pub fail_on_oversized: bool,
+
+    /// A shell command to run after each successful upload. The asset's ID,
+    /// name, and input path are passed in the TARMAC_ASSET_ID,
+    /// TARMAC_ASSET_NAME, and TARMAC_PATH environment variables.
+    #[structopt(long)]
+    pub post_upload_hook: Option<String>,
+
+    /// Treat a failing post-upload hook as an error, instead of only warning
+    /// about it. The upload is still recorded in the manifest.
+    #[structopt(long, requires = "post-upload-hook")]
+    pub strict_hooks: bool,
+
+    /// Skip locking the project while syncing. Only use this if you're sure
+    /// that no other Tarmac process will sync the same project at once.
+    #[structopt(long)]
+    pub no_lock: bool,
+
+    /// Upload images as their pixels are stored, instead of rotating and
+    /// flipping them to match their EXIF orientation.
+    #[structopt(long)]
+    pub no_exif_rotate: bool,
+
+    /// Print the settings that the sync would use as JSON, including every
+    /// project config, then exit without syncing. Secrets like the auth cookie
+    /// are printed as `***`.
+    #[structopt(long)]
+    pub print_config: bool,
+
+    /// The paths to Tarmac configs, or folders containing Tarmac projects.
+    /// Each project is synced in turn. Defaults to the current directory.
+    pub config_paths: Vec<PathBuf>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SyncTarget {
     Roblox,
     None,
     Debug,
     Local,
+    Cdn,
+}
+
+impl SyncTarget {
+    /// Every target, in the order they're listed in help messages.
+    pub const ALL: &'static [SyncTarget] = &[
+        SyncTarget::Roblox,
+        SyncTarget::Local,
+        SyncTarget::None,
+        SyncTarget::Debug,
+        SyncTarget::Cdn,
+    ];
+
+    /// A short explanation of what syncing to this target does.
+    pub fn description(&self) -> &'static str {
+        match self {
+            SyncTarget::Roblox => "Upload to Roblox.com",
+            SyncTarget::Local => "Copy to the content folder of the installed Roblox Studio",
+            SyncTarget::None => "Upload nothing, and fail if any inputs are unsynced",
+            SyncTarget::Debug => "Copy to a local debug folder",
+            SyncTarget::Cdn => "Write assets named after their hashes, for another tool to host",
+        }
+    }
+
+    /// Lists every target with its description, one per line.
+    pub fn list() -> String {
+        SyncTarget::ALL
+            .iter()
+            .map(|target| format!("  {:<8}{}", target.as_str(), target.description()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The name used to pick this target on the command line.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncTarget::Roblox => "roblox",
+            SyncTarget::None => "none",
+            SyncTarget::Debug => "debug",
+            SyncTarget::Local => "local",
+            SyncTarget::Cdn => "cdn",
+        }
+    }
 }
 
 impl FromStr for SyncTarget {
     type Err = String;
 
     fn from_str(value: &str) -> Result<SyncTarget, Self::Err> {
-        match value {
-            "roblox" => Ok(SyncTarget::Roblox),
-            "none" => Ok(SyncTarget::None),
-            "debug" => Ok(SyncTarget::Debug),
-            "local" => Ok(SyncTarget::Local),
-
-            _ => Err(String::from(
-                "Invalid sync target. Valid options are roblox, local, none, and debug.",
-            )),
-        }
+        SyncTarget::ALL
+            .iter()
+            .copied()
+            .find(|target| target.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid sync target '{}'. Valid targets are:\n{}\n\nFor example: tarmac sync --target roblox",
+                    value,
+                    SyncTarget::list()
+                )
+            })
     }
 }
 
@@ -123,6 +505,87 @@ pub struct CreateCacheMapOptions {
     /// A path to a file to contain the cache mapping.
     #[structopt(long = "index-file")]
     pub index_file: PathBuf,
+
+    /// The format to write the cache mapping in.
+    ///
+    /// Options:
+    ///
+    /// - json: An object mapping asset IDs to paths
+    ///
+    /// - csv: A table with an asset_id and a path column
+    #[structopt(long, default_value = "json")]
+    pub format: CacheMapFormat,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CacheMapFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for CacheMapFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<CacheMapFormat, Self::Err> {
+        match value {
+            "json" => Ok(CacheMapFormat::Json),
+            "csv" => Ok(CacheMapFormat::Csv),
+
+            _ => Err(String::from(
+                "Invalid cache map format. Valid options are json and csv.",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CodegenCommandOptions {
+    /// Write one file per top-level folder, like `tarmac sync
+    /// --codegen-split-root`.
+    #[structopt(long)]
+    pub codegen_split_root: bool,
+
+    /// Skip locking the project while generating code.
+    #[structopt(long)]
+    pub no_lock: bool,
+
+    /// The path to a Tarmac config, or a folder containing a Tarmac project.
+    pub config_path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct MigrateOptions {
+    /// The folder containing the project's manifest. Defaults to the current
+    /// directory.
+    pub project_path: Option<PathBuf>,
+
+    /// Skip locking the project while migrating its manifest.
+    #[structopt(long)]
+    pub no_lock: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DoctorOptions {
+    /// The content folder to check, like `tarmac sync --content-path`.
+    /// Defaults to the content folder of the installed copy of Roblox Studio.
+    #[structopt(long)]
+    pub content_path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct BackupOptions {
+    /// The folder containing the project's manifest. Defaults to the current
+    /// directory.
+    pub project_path: Option<PathBuf>,
+
+    /// The folder to write downloaded assets into. Each input is written to
+    /// the same relative path it has in the project.
+    #[structopt(long = "output-dir")]
+    pub output_dir: PathBuf,
+
+    /// The number of assets to download at the same time.
+    #[structopt(long, default_value = "4")]
+    pub jobs: usize,
 }
 
 #[derive(Debug, StructOpt)]
@@ -132,4 +595,23 @@ pub struct AssetListOptions {
     /// A path to a file to put the asset list.
     #[structopt(long = "output")]
     pub output: PathBuf,
+
+    /// Write a tab-separated table with the name, width, height, and DPI scale
+    /// of each input next to its asset ID, instead of just the IDs.
+    #[structopt(long)]
+    pub verbose_list: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct InitOptions {
+    /// The folder to create the project in. Defaults to the current directory.
+    pub project_path: Option<PathBuf>,
+
+    /// The name of the project. Defaults to the name of the project's folder.
+    #[structopt(long)]
+    pub name: Option<String>,
+
+    /// Overwrite the tarmac.toml file if one already exists.
+    #[structopt(long)]
+    pub force: bool,
 }
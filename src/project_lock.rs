@@ -0,0 +1,67 @@
+//! Advisory lock that keeps multiple Tarmac processes from syncing the same
+//! project at the same time and racing to write its manifest.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use fs2::FileExt;
+use fs_err::File;
+use thiserror::Error;
+
+static LOCK_FILENAME: &str = ".tarmac.lock";
+
+/// Holds an exclusive lock on a Tarmac project for as long as it's alive.
+///
+/// The lock file itself is left in place when the lock is released, since
+/// deleting it could let two processes lock different files with the same path.
+/// The operating system releases the lock if Tarmac exits unexpectedly.
+#[derive(Debug)]
+pub struct ProjectLock {
+    file: File,
+}
+
+impl ProjectLock {
+    /// Try to lock the project in the given folder, failing immediately if
+    /// another process already holds the lock.
+    pub fn acquire<P: AsRef<Path>>(folder_path: P) -> Result<Self, ProjectLockError> {
+        let path = folder_path.as_ref().join(LOCK_FILENAME);
+        let file = File::create(&path)?;
+
+        match file.file().try_lock_exclusive() {
+            Ok(()) => {
+                log::trace!("Acquired project lock at {}", path.display());
+                Ok(Self { file })
+            }
+            Err(err) if err.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                Err(ProjectLockError::Locked { path })
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Drop for ProjectLock {
+    fn drop(&mut self) {
+        if let Err(err) = self.file.file().unlock() {
+            log::warn!("Failed to release project lock: {}", err);
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProjectLockError {
+    #[error(
+        "Another Tarmac process is already syncing this project (lock file: {}). \
+        If you're sure no other sync is running, pass --no-lock.",
+        .path.display()
+    )]
+    Locked { path: PathBuf },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+}
@@ -1,23 +1,37 @@
 use std::{
     borrow::Cow,
+    collections::HashMap,
     io,
     path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use fs_err as fs;
 use reqwest::StatusCode;
 use roblox_install::RobloxStudio;
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::data::AssetId;
+use crate::asset_name::AssetName;
+use crate::data::{AssetCreator, AssetId};
 use crate::roblox_web_api::{ImageUploadData, RobloxApiClient, RobloxApiError};
 
 pub trait SyncBackend {
     fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error>;
 }
 
+impl<B: SyncBackend + ?Sized> SyncBackend for &mut B {
+    fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
+        (**self).upload(data)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UploadResponse {
     pub id: AssetId,
@@ -28,18 +42,70 @@ pub struct UploadInfo {
     pub name: String,
     pub contents: Vec<u8>,
     pub hash: String,
+
+    /// The input that this upload was created from, if it was created from
+    /// exactly one input. Packed spritesheets have no single source.
+    pub source: Option<AssetName>,
+
+    /// The DPI scale of the input or inputs that this upload was created from.
+    pub dpi_scale: u32,
+
+    /// Who the uploaded asset should belong to.
+    pub creator: AssetCreator,
 }
 
+/// The description given to assets uploaded by `tarmac sync`.
+pub const DEFAULT_DESCRIPTION: &str = "Uploaded by Tarmac.";
+
 pub struct RobloxSyncBackend<'a> {
     api_client: &'a mut RobloxApiClient,
-    upload_to_group_id: Option<u64>,
+    use_decal_id: bool,
+    moderation_fallback_name: String,
+    moderation_retries: u32,
+    description: String,
+    include_hash_in_description: bool,
 }
 
 impl<'a> RobloxSyncBackend<'a> {
-    pub fn new(api_client: &'a mut RobloxApiClient, upload_to_group_id: Option<u64>) -> Self {
+    pub fn new(
+        api_client: &'a mut RobloxApiClient,
+        use_decal_id: bool,
+        moderation_fallback_name: String,
+        moderation_retries: u32,
+    ) -> Self {
+        if use_decal_id {
+            log::warn!("use-decal-id is enabled: Tarmac will record Decal asset IDs instead of Image asset IDs.");
+            log::warn!("Decal IDs only resolve to images in some contexts, like Decal.Texture set from Studio.");
+        }
+
         Self {
             api_client,
-            upload_to_group_id,
+            use_decal_id,
+            moderation_fallback_name,
+            moderation_retries,
+            description: DEFAULT_DESCRIPTION.to_owned(),
+            include_hash_in_description: false,
+        }
+    }
+
+    /// Appends the given text to the description of every uploaded asset.
+    pub fn with_description_suffix(mut self, suffix: &str) -> Self {
+        self.description = format!("{} {}", DEFAULT_DESCRIPTION, suffix);
+        self
+    }
+
+    /// Ends the description of every uploaded asset with the hash of its
+    /// contents, so that assets can be matched back to their source files.
+    pub fn with_hash_in_description(mut self, include_hash: bool) -> Self {
+        self.include_hash_in_description = include_hash;
+        self
+    }
+
+    fn description(&self, data: &UploadInfo) -> String {
+        if self.include_hash_in_description {
+            format!("{} (hash: {})", self.description, data.hash)
+        } else {
+            self.description.clone()
         }
     }
 }
@@ -47,35 +113,52 @@ impl<'a> RobloxSyncBackend<'a> {
 impl<'a> SyncBackend for RobloxSyncBackend<'a> {
     fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
         log::info!("Uploading {} to Roblox", &data.name);
+        log::trace!("Contents of {} have hash {}", &data.name, &data.hash);
+
+        let group_id = match data.creator {
+            AssetCreator::User => None,
+            AssetCreator::Group(group_id) => Some(group_id),
+        };
 
-        let result = self
-            .api_client
-            .upload_image_with_moderation_retry(ImageUploadData {
+        let description = self.description(&data);
+        let result = self.api_client.upload_image_with_moderation_retry(
+            ImageUploadData {
                 image_data: Cow::Owned(data.contents),
                 name: &data.name,
-                description: "Uploaded by Tarmac.",
-                group_id: self.upload_to_group_id,
-            });
+                description: &description,
+                group_id,
+            },
+            &self.moderation_fallback_name,
+            self.moderation_retries,
+        );
 
         match result {
             Ok(response) => {
-                log::info!(
-                    "Uploaded {} to ID {}",
-                    &data.name,
+                let id = if self.use_decal_id {
+                    log::info!(
+                        "Uploaded {} to Decal ID {} (Image ID {})",
+                        &data.name,
+                        response.asset_id,
+                        response.backing_asset_id
+                    );
+
+                    response.asset_id
+                } else {
+                    log::info!(
+                        "Uploaded {} to ID {}",
+                        &data.name,
+                        response.backing_asset_id
+                    );
+
                     response.backing_asset_id
-                );
+                };
 
                 Ok(UploadResponse {
-                    id: AssetId::Id(response.backing_asset_id),
+                    id: AssetId::Id(id),
                 })
             }
 
-            Err(RobloxApiError::ResponseError {
-                status: StatusCode::TOO_MANY_REQUESTS,
-                ..
-            }) => Err(Error::RateLimited),
-
-            Err(err) => Err(err.into()),
+            Err(err) => Err(classify_api_error(err)),
         }
     }
 }
@@ -83,16 +166,79 @@ impl<'a> SyncBackend for RobloxSyncBackend<'a> {
 pub struct LocalSyncBackend {
     content_path: PathBuf,
     scope: Option<String>,
+    emit_metadata: bool,
+
+    /// The file names written so far, keyed by their lowercase form, used to
+    /// find names that would collide on case-insensitive filesystems.
+    written_names: HashMap<String, String>,
+}
+
+/// Metadata written next to each asset synced by `LocalSyncBackend` as
+/// `<name>.meta.json` when `local-emit-metadata` is enabled.
+///
+/// This format is intended to be read by other tools, so fields should only
+/// ever be added to it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalAssetMetadata<'a> {
+    /// The hexadecimal encoded hash of the asset's source contents.
+    pub hash: &'a str,
+
+    /// The path of the input this asset was created from, relative to the
+    /// project root. Absent for packed spritesheets.
+    pub source_path: Option<&'a AssetName>,
+
+    /// The DPI scale of the input or inputs this asset was created from.
+    pub dpi_scale: u32,
 }
 
 impl LocalSyncBackend {
-    pub fn new(scope: Option<String>) -> Result<LocalSyncBackend, Error> {
-        RobloxStudio::locate()
-            .map(|studio| LocalSyncBackend {
-                content_path: studio.content_path().into(),
-                scope,
-            })
-            .map_err(|error| error.into())
+    /// Creates a backend that writes to the content folder of the Roblox Studio
+    /// installed on this machine.
+    pub fn new(scope: Option<String>, emit_metadata: bool) -> Result<LocalSyncBackend, Error> {
+        let studio = RobloxStudio::locate()?;
+
+        Ok(Self::with_content_path(
+            studio.content_path().into(),
+            scope,
+            emit_metadata,
+        ))
+    }
+
+    /// Creates a backend that writes to the given content folder, without
+    /// looking for Roblox Studio.
+    pub fn with_content_path(
+        content_path: PathBuf,
+        scope: Option<String>,
+        emit_metadata: bool,
+    ) -> LocalSyncBackend {
+        LocalSyncBackend {
+            content_path,
+            scope,
+            emit_metadata,
+            written_names: HashMap::new(),
+        }
+    }
+
+    pub fn content_path(&self) -> &Path {
+        &self.content_path
+    }
+
+    /// Checks that files can be written to the content folder by writing and
+    /// removing an empty one, so that a sync can fail before doing any work
+    /// instead of on its first upload.
+    pub fn check_writable(&self) -> Result<(), Error> {
+        let folder = self.content_path.join(".tarmac");
+        let probe_path = folder.join(format!(".write-test-{}", std::process::id()));
+
+        let result = fs::create_dir_all(&folder)
+            .and_then(|_| fs::write(&probe_path, b""))
+            .and_then(|_| fs::remove_file(&probe_path));
+
+        result.map_err(|source| Error::ContentPathNotWritable {
+            path: self.content_path.clone(),
+            source,
+        })
     }
 
     fn get_asset_path(&self, data: &UploadInfo) -> PathBuf {
@@ -104,13 +250,40 @@ impl LocalSyncBackend {
         path
     }
 
+    /// File names keep the casing of the input they came from exactly, so that
+    /// lookups work on case-sensitive filesystems.
     fn get_asset_file_name(&self, data: &UploadInfo) -> String {
         format!("{}.png", data.name)
     }
+
+    /// Records that a file with the given name was written, returning the name
+    /// of an earlier file that differs from it only by case, if there was one.
+    fn record_file_name(&mut self, file_name: &str) -> Option<&str> {
+        let existing = self
+            .written_names
+            .entry(file_name.to_lowercase())
+            .or_insert_with(|| file_name.to_owned());
+
+        if existing == file_name {
+            None
+        } else {
+            Some(existing)
+        }
+    }
 }
 
 impl SyncBackend for LocalSyncBackend {
     fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
+        let file_name = self.get_asset_file_name(&data);
+        if let Some(existing) = self.record_file_name(&file_name) {
+            log::warn!(
+                "{} and {} differ only by case and will overwrite each other \
+                 on case-insensitive filesystems",
+                existing,
+                file_name
+            );
+        }
+
         let asset_path = self.get_asset_path(&data);
         let file_path = self.content_path.join(&asset_path);
         let parent = file_path
@@ -122,6 +295,17 @@ impl SyncBackend for LocalSyncBackend {
 
         log::info!("Written {} to path {}", &data.name, file_path.display());
 
+        if self.emit_metadata {
+            let metadata = LocalAssetMetadata {
+                hash: &data.hash,
+                source_path: data.source.as_ref(),
+                dpi_scale: data.dpi_scale,
+            };
+
+            let metadata_path = file_path.with_file_name(format!("{}.meta.json", data.name));
+            fs::write(&metadata_path, serde_json::to_vec_pretty(&metadata)?)?;
+        }
+
         Ok(UploadResponse {
             id: AssetId::Path(asset_path),
         })
@@ -137,12 +321,25 @@ impl SyncBackend for NoneSyncBackend {
 }
 
 pub struct DebugSyncBackend {
+    folder: PathBuf,
     last_id: u64,
 }
 
+impl Default for DebugSyncBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DebugSyncBackend {
     pub fn new() -> Self {
-        Self { last_id: 0 }
+        Self::with_folder(PathBuf::from(".tarmac-debug"))
+    }
+
+    /// Creates a backend that copies assets into the given folder instead of
+    /// `.tarmac-debug` in the working directory.
+    pub fn with_folder(folder: PathBuf) -> Self {
+        Self { folder, last_id: 0 }
     }
 }
 
@@ -153,10 +350,9 @@ impl SyncBackend for DebugSyncBackend {
         self.last_id += 1;
         let id = self.last_id;
 
-        let path = Path::new(".tarmac-debug");
-        fs::create_dir_all(path)?;
+        fs::create_dir_all(&self.folder)?;
 
-        let file_path = path.join(id.to_string());
+        let file_path = self.folder.join(id.to_string());
         fs::write(&file_path, &data.contents)?;
 
         Ok(UploadResponse {
@@ -165,48 +361,392 @@ impl SyncBackend for DebugSyncBackend {
     }
 }
 
-/// Performs the retry logic for rate limitation errors. The struct wraps a SyncBackend so that
-/// when a RateLimited error occurs, the thread sleeps for a moment and then tries to reupload the
-/// data.
+/// Doesn't upload anything. Instead, writes each asset's contents to a folder
+/// named after its hash and keeps a list of them, so that another tool can
+/// upload them or host them somewhere else.
+///
+/// Assets are given placeholder IDs derived from their hashes, which aren't
+/// recorded in the manifest.
+pub struct CdnSyncBackend {
+    project_path: PathBuf,
+    entries: Vec<CdnEntry>,
+}
+
+/// An entry in the plan written by CdnSyncBackend.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CdnEntry {
+    /// A human-friendly name for the asset.
+    pub name: String,
+
+    /// The hexadecimal encoded hash of the asset's source contents.
+    pub hash: String,
+
+    /// Where the asset's contents were written, relative to the project.
+    pub content_path: String,
+
+    /// The input this asset was created from, relative to the project. Absent
+    /// for packed spritesheets.
+    pub source_path: Option<AssetName>,
+}
+
+impl CdnSyncBackend {
+    pub fn new(project_path: PathBuf) -> Self {
+        Self {
+            project_path,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Writes the list of every asset that was "uploaded" to
+    /// `tarmac-cdn.json` in the project folder.
+    pub fn write_plan(&mut self) -> Result<(), Error> {
+        self.entries
+            .sort_by(|a, b| a.content_path.cmp(&b.content_path));
+
+        let plan_path = self.project_path.join("tarmac-cdn.json");
+        fs::write(&plan_path, serde_json::to_vec_pretty(&self.entries)?)?;
+
+        log::info!("Wrote CDN plan to {}", plan_path.display());
+
+        Ok(())
+    }
+}
+
+impl SyncBackend for CdnSyncBackend {
+    fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
+        let content_path = format!(".tarmac-cdn/{}.png", data.hash);
+        log::info!("Writing {} to {}", &data.name, content_path);
+
+        let file_path = self.project_path.join(&content_path);
+        fs::create_dir_all(file_path.parent().unwrap())?;
+        fs::write(&file_path, &data.contents)?;
+
+        self.entries.push(CdnEntry {
+            name: data.name,
+            hash: data.hash.clone(),
+            content_path,
+            source_path: data.source,
+        });
+
+        Ok(UploadResponse {
+            id: AssetId::Placeholder(data.hash),
+        })
+    }
+}
+
+/// Performs the retry logic for rate limitation and network errors. The struct wraps a SyncBackend
+/// so that when a retryable error occurs, the thread sleeps for a moment and then tries to reupload
+/// the data.
 pub struct RetryBackend<InnerSyncBackend> {
     inner: InnerSyncBackend,
     delay: Duration,
     attempts: usize,
+    jitter: f64,
+
+    /// Whether uploads that failed with a 5xx response are retried too.
+    retry_server_errors: bool,
+
+    /// The number of retries left across every upload, if they're limited.
+    /// Shared between backends so that it can cover a whole sync.
+    budget: Option<Arc<AtomicUsize>>,
+
+    /// Returns a random number in the range [0, 1). Replaceable for testing.
+    random: Box<dyn FnMut() -> f64>,
+
+    /// Blocks for the given duration. Replaceable for testing.
+    sleep: Box<dyn FnMut(Duration)>,
 }
 
 impl<InnerSyncBackend> RetryBackend<InnerSyncBackend> {
     /// Creates a new backend from another SyncBackend. The max_retries parameter gives the number
     /// of times the backend will try again (so given 0, it acts just as the original SyncBackend).
     /// The delay parameter provides the amount of time to wait between each upload attempt.
-    pub fn new(inner: InnerSyncBackend, max_retries: usize, delay: Duration) -> Self {
+    /// The jitter parameter is the fraction of the delay, between 0 and 1, that each wait may
+    /// randomly be shortened or lengthened by, so that many processes being rate limited at once
+    /// don't all retry at the same moment.
+    pub fn new(inner: InnerSyncBackend, max_retries: usize, delay: Duration, jitter: f64) -> Self {
         Self {
             inner,
             delay,
             attempts: max_retries + 1,
+            jitter: jitter.clamp(0.0, 1.0),
+            retry_server_errors: false,
+            budget: None,
+            random: Box::new(rand::random::<f64>),
+            sleep: Box::new(thread::sleep),
+        }
+    }
+
+    /// Limits the total number of retries to the count in the given budget,
+    /// which is used up by every backend it's shared with. Once it runs out,
+    /// uploads are only attempted once.
+    pub fn with_retry_budget(mut self, budget: Arc<AtomicUsize>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Also retries uploads that failed because Roblox responded with a server
+    /// error. These usually go away on their own, but some uploads fail with
+    /// one every time, so they're only retried when asked.
+    pub fn with_server_error_retries(mut self, retry_server_errors: bool) -> Self {
+        self.retry_server_errors = retry_server_errors;
+        self
+    }
+
+    /// Whether an upload that failed with the given error should be tried
+    /// again.
+    fn should_retry(&self, err: &Error) -> bool {
+        err.is_retryable() || (self.retry_server_errors && matches!(err, Error::ServerError { .. }))
+    }
+
+    /// Takes one retry from the budget, returning false if there are none left.
+    fn take_retry(&self) -> bool {
+        match &self.budget {
+            Some(budget) => budget
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok(),
+            None => true,
         }
     }
+
+    /// Replaces the source of random numbers used for jitter.
+    #[cfg(test)]
+    fn with_random(mut self, random: impl FnMut() -> f64 + 'static) -> Self {
+        self.random = Box::new(random);
+        self
+    }
+
+    /// Replaces the function used to wait between attempts.
+    #[cfg(test)]
+    fn with_sleep(mut self, sleep: impl FnMut(Duration) + 'static) -> Self {
+        self.sleep = Box::new(sleep);
+        self
+    }
+
+    /// The amount of time to wait before the next attempt, with jitter applied.
+    fn next_delay(&mut self) -> Duration {
+        if self.jitter == 0.0 {
+            return self.delay;
+        }
+
+        // Maps the random number onto [1 - jitter, 1 + jitter).
+        let factor = 1.0 + self.jitter * (2.0 * (self.random)() - 1.0);
+        self.delay.mul_f64(factor)
+    }
 }
 
 impl<InnerSyncBackend: SyncBackend> SyncBackend for RetryBackend<InnerSyncBackend> {
     fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
-        for index in 0..self.attempts {
-            if index != 0 {
-                log::info!(
-                    "tarmac is being rate limited, retrying upload ({}/{})",
-                    index,
-                    self.attempts - 1
-                );
-                thread::sleep(self.delay);
+        let mut attempt = 1;
+
+        loop {
+            match self.inner.upload(data.clone()) {
+                Err(err) if self.should_retry(&err) && attempt < self.attempts => {
+                    if !self.take_retry() {
+                        log::warn!(
+                            "Tarmac has used up every retry allowed by --max-total-retries, so it won't retry this upload"
+                        );
+                        return Err(err);
+                    }
+
+                    let reason = match err {
+                        Error::Timeout => "timed out",
+                        Error::Transient { .. } => "lost its connection to Roblox",
+                        Error::ServerError { .. } => "got a server error from Roblox",
+                        _ => "is being rate limited",
+                    };
+
+                    log::info!(
+                        "tarmac {}, retrying upload ({}/{})",
+                        reason,
+                        attempt,
+                        self.attempts - 1
+                    );
+                    let delay = self.next_delay();
+                    (self.sleep)(delay);
+                    attempt += 1;
+                }
+                result => return result,
             }
-            let result = self.inner.upload(data.clone());
+        }
+    }
+}
+
+/// Refuses to start any more uploads once the given flag is set, which happens
+/// when the user asks Tarmac to stop with Ctrl-C. Uploads that have already
+/// started are allowed to finish.
+pub struct InterruptibleBackend<InnerSyncBackend> {
+    inner: InnerSyncBackend,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl<InnerSyncBackend> InterruptibleBackend<InnerSyncBackend> {
+    pub fn new(inner: InnerSyncBackend, interrupted: Arc<AtomicBool>) -> Self {
+        Self { inner, interrupted }
+    }
+}
+
+impl<InnerSyncBackend: SyncBackend> SyncBackend for InterruptibleBackend<InnerSyncBackend> {
+    fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
+        if self.interrupted.load(Ordering::SeqCst) {
+            return Err(Error::Interrupted);
+        }
+
+        self.inner.upload(data)
+    }
+}
+
+/// Runs a shell command after each successful upload made by the wrapped
+/// SyncBackend, describing the uploaded asset with environment variables.
+///
+/// A failing hook is only warned about unless the backend is strict, in which
+/// case the failure is kept so that it can be reported as an error once the
+/// sync is done. Either way, the upload itself still succeeds, since the asset
+/// exists and its ID needs to make it into the manifest.
+pub struct HookBackend<InnerSyncBackend> {
+    inner: InnerSyncBackend,
+    command: String,
+    strict: bool,
+    failures: Vec<Error>,
+}
 
-            match result {
-                Err(Error::RateLimited) => {}
-                _ => return result,
+impl<InnerSyncBackend> HookBackend<InnerSyncBackend> {
+    pub fn new(inner: InnerSyncBackend, command: String, strict: bool) -> Self {
+        Self {
+            inner,
+            command,
+            strict,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Takes the hook failures kept by a strict backend so far.
+    pub fn take_failures(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.failures)
+    }
+
+    fn run_hook(&self, name: &str, source: Option<&AssetName>, id: &AssetId) -> Result<(), Error> {
+        let asset_id = match id {
+            AssetId::Id(id) => id.to_string(),
+            other => other.to_string(),
+        };
+
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+
+        command
+            .arg(&self.command)
+            .env("TARMAC_ASSET_ID", asset_id)
+            .env("TARMAC_ASSET_NAME", name);
+
+        if let Some(source) = source {
+            command.env("TARMAC_PATH", source.to_string());
+        }
+
+        let reason = match command.status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => format!("it exited with {}", status),
+            Err(err) => err.to_string(),
+        };
+
+        Err(Error::Hook {
+            command: self.command.clone(),
+            name: name.to_owned(),
+            reason,
+        })
+    }
+}
+
+impl<InnerSyncBackend: SyncBackend> SyncBackend for HookBackend<InnerSyncBackend> {
+    fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
+        let name = data.name.clone();
+        let source = data.source.clone();
+        let response = self.inner.upload(data)?;
+
+        if let Err(err) = self.run_hook(&name, source.as_ref(), &response.id) {
+            if self.strict {
+                self.failures.push(err);
+            } else {
+                log::warn!("{}", err);
             }
         }
 
-        Err(Error::RateLimited)
+        Ok(response)
+    }
+}
+
+/// Limits how many uploads the wrapped SyncBackend is asked to make per minute,
+/// so that Tarmac stays under Roblox's quotas instead of relying on retrying
+/// after being rate limited.
+///
+/// Uploads are spaced out evenly: each one waits until at least
+/// `60 / max_per_minute` seconds have passed since the previous one started.
+pub struct ThrottleBackend<InnerSyncBackend> {
+    inner: InnerSyncBackend,
+    interval: Duration,
+    next_upload: Option<Instant>,
+
+    /// Returns the current time. Replaceable for testing.
+    now: Box<dyn FnMut() -> Instant>,
+
+    /// Blocks for the given duration. Replaceable for testing.
+    sleep: Box<dyn FnMut(Duration)>,
+}
+
+impl<InnerSyncBackend> ThrottleBackend<InnerSyncBackend> {
+    pub fn new(inner: InnerSyncBackend, max_per_minute: u32) -> Self {
+        Self {
+            inner,
+            interval: Duration::from_secs(60) / max_per_minute.max(1),
+            next_upload: None,
+            now: Box::new(Instant::now),
+            sleep: Box::new(thread::sleep),
+        }
+    }
+
+    /// Replaces the clock used to decide how long to wait.
+    #[cfg(test)]
+    fn with_clock(
+        mut self,
+        now: impl FnMut() -> Instant + 'static,
+        sleep: impl FnMut(Duration) + 'static,
+    ) -> Self {
+        self.now = Box::new(now);
+        self.sleep = Box::new(sleep);
+        self
+    }
+}
+
+impl<InnerSyncBackend: SyncBackend> SyncBackend for ThrottleBackend<InnerSyncBackend> {
+    fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
+        let now = (self.now)();
+
+        let start = match self.next_upload {
+            Some(next_upload) if next_upload > now => {
+                let wait = next_upload - now;
+                log::debug!(
+                    "Waiting {:.1}s before uploading {} to stay under the request limit",
+                    wait.as_secs_f64(),
+                    data.name
+                );
+                (self.sleep)(wait);
+                next_upload
+            }
+            _ => now,
+        };
+
+        self.next_upload = Some(start + self.interval);
+        self.inner.upload(data)
     }
 }
 
@@ -218,6 +758,31 @@ pub enum Error {
     #[error("Tarmac was rate-limited trying to upload assets. Try again in a little bit.")]
     RateLimited,
 
+    #[error("Tarmac timed out waiting for Roblox to respond to an upload.")]
+    Timeout,
+
+    #[error("Tarmac lost its connection to Roblox while uploading an asset.")]
+    Transient { source: RobloxApiError },
+
+    #[error("Roblox had a problem handling an upload: {source}")]
+    ServerError { source: RobloxApiError },
+
+    #[error("Tarmac was interrupted before it could upload this asset.")]
+    Interrupted,
+
+    #[error("The post-upload hook `{command}` failed for {name}: {reason}")]
+    Hook {
+        command: String,
+        name: String,
+        reason: String,
+    },
+
+    #[error(
+        "Tarmac can't write to the content folder {}. Check that it exists and that you have permission to write to it, or pick another one with --content-path.",
+        .path.display()
+    )]
+    ContentPathNotWritable { path: PathBuf, source: io::Error },
+
     #[error(transparent)]
     StudioInstall {
         #[from]
@@ -231,20 +796,108 @@ pub enum Error {
     },
 
     #[error(transparent)]
-    RobloxError {
+    RobloxApi {
         #[from]
         source: RobloxApiError,
     },
+
+    #[error(transparent)]
+    Json {
+        #[from]
+        source: serde_json::Error,
+    },
+}
+
+impl Error {
+    /// Whether an upload that failed with this error may succeed if it's tried
+    /// again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Error::RateLimited | Error::Timeout | Error::Transient { .. }
+        )
+    }
+}
+
+/// Sorts an error from the Roblox API by whether the upload could succeed if
+/// it's tried again. Rate limits, timeouts, and dropped connections are worth
+/// retrying, but other errors like a 4xx response will happen again every time.
+/// Server errors are kept apart, since a 5xx response can also mean that Roblox
+/// rejects the upload itself.
+fn classify_api_error(err: RobloxApiError) -> Error {
+    match err {
+        RobloxApiError::ResponseError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            ..
+        } => Error::RateLimited,
+        RobloxApiError::Timeout { .. } => Error::Timeout,
+        RobloxApiError::ResponseError { status, .. } if status.is_server_error() => {
+            Error::ServerError { source: err }
+        }
+        RobloxApiError::Http { ref source } if is_connection_error(source) => {
+            Error::Transient { source: err }
+        }
+        err => err.into(),
+    }
+}
+
+/// Tells whether a request failed because of a problem with the connection,
+/// like a failed DNS lookup or a reset connection, rather than a problem with
+/// the request itself.
+fn is_connection_error(err: &reqwest::Error) -> bool {
+    match err.get_ref() {
+        Some(inner) => has_io_cause(inner),
+        None => false,
+    }
+}
+
+/// Tells whether an error was caused by an I/O error anywhere in its chain of
+/// sources.
+fn has_io_cause(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut cause = Some(err);
+
+    while let Some(err) = cause {
+        if err.is::<io::Error>() {
+            return true;
+        }
+
+        cause = err.source();
+    }
+
+    false
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    struct AlwaysSucceeds;
+
+    impl SyncBackend for AlwaysSucceeds {
+        fn upload(&mut self, _data: UploadInfo) -> Result<UploadResponse, Error> {
+            Ok(UploadResponse {
+                id: AssetId::Id(42),
+            })
+        }
+    }
+
+    fn upload_info() -> UploadInfo {
+        UploadInfo {
+            name: "foo".to_owned(),
+            contents: Vec::new(),
+            hash: "hash".to_owned(),
+            source: None,
+            dpi_scale: 1,
+            creator: AssetCreator::User,
+        }
+    }
+
     #[allow(unused_must_use)]
     mod test_retry_backend {
         use super::*;
 
+        use std::{cell::RefCell, rc::Rc};
+
         struct CountUploads<'a> {
             counter: &'a mut usize,
             results: Vec<Result<UploadResponse, Error>>,
@@ -272,25 +925,62 @@ mod test {
             }
         }
 
-        fn any_upload_info() -> UploadInfo {
-            UploadInfo {
-                name: "foo".to_owned(),
-                contents: Vec::new(),
-                hash: "hash".to_owned(),
+        fn retry_duration() -> Duration {
+            Duration::from_millis(1)
+        }
+
+        #[test]
+        fn jitter_keeps_delay_within_bounds() {
+            let slept = Rc::new(RefCell::new(Vec::new()));
+            let recorded = Rc::clone(&slept);
+            let mut randoms = vec![0.0, 0.999, 0.5].into_iter();
+
+            let mut counter = 0;
+            let inner = CountUploads::new(&mut counter).with_results(vec![
+                Err(Error::RateLimited),
+                Err(Error::RateLimited),
+                Err(Error::RateLimited),
+            ]);
+            let mut backend = RetryBackend::new(inner, 3, Duration::from_millis(1000), 0.2)
+                .with_random(move || randoms.next().unwrap())
+                .with_sleep(move |duration| recorded.borrow_mut().push(duration));
+
+            backend.upload(upload_info());
+
+            let slept = slept.borrow();
+            assert_eq!(slept.len(), 3);
+            assert_eq!(slept[0], Duration::from_millis(800));
+            assert_eq!(slept[2], Duration::from_millis(1000));
+
+            for duration in slept.iter() {
+                assert!(*duration >= Duration::from_millis(800));
+                assert!(*duration < Duration::from_millis(1200));
             }
         }
 
-        fn retry_duration() -> Duration {
-            Duration::from_millis(1)
+        #[test]
+        fn no_jitter_sleeps_for_exact_delay() {
+            let slept = Rc::new(RefCell::new(Vec::new()));
+            let recorded = Rc::clone(&slept);
+
+            let mut counter = 0;
+            let inner = CountUploads::new(&mut counter)
+                .with_results(vec![Err(Error::RateLimited), Err(Error::RateLimited)]);
+            let mut backend = RetryBackend::new(inner, 2, Duration::from_millis(500), 0.0)
+                .with_sleep(move |duration| recorded.borrow_mut().push(duration));
+
+            backend.upload(upload_info());
+
+            assert_eq!(*slept.borrow(), vec![Duration::from_millis(500); 2]);
         }
 
         #[test]
         fn upload_at_least_once() {
             let mut counter = 0;
             let mut backend =
-                RetryBackend::new(CountUploads::new(&mut counter), 0, retry_duration());
+                RetryBackend::new(CountUploads::new(&mut counter), 0, retry_duration(), 0.0);
 
-            backend.upload(any_upload_info());
+            backend.upload(upload_info());
 
             assert_eq!(counter, 1);
         }
@@ -303,9 +993,9 @@ mod test {
                 Err(Error::RateLimited),
                 Err(Error::NoneBackend),
             ]);
-            let mut backend = RetryBackend::new(inner, 5, retry_duration());
+            let mut backend = RetryBackend::new(inner, 5, retry_duration(), 0.0);
 
-            backend.upload(any_upload_info());
+            backend.upload(upload_info());
 
             assert_eq!(counter, 3);
         }
@@ -321,9 +1011,9 @@ mod test {
                 Err(Error::RateLimited),
                 Ok(success.clone()),
             ]);
-            let mut backend = RetryBackend::new(inner, 5, retry_duration());
+            let mut backend = RetryBackend::new(inner, 5, retry_duration(), 0.0);
 
-            let upload_result = backend.upload(any_upload_info()).unwrap();
+            let upload_result = backend.upload(upload_info()).unwrap();
 
             assert_eq!(counter, 3);
             assert_eq!(upload_result, success);
@@ -338,15 +1028,274 @@ mod test {
                 Err(Error::RateLimited),
                 Err(Error::RateLimited),
             ]);
-            let mut backend = RetryBackend::new(inner, 2, retry_duration());
+            let mut backend = RetryBackend::new(inner, 2, retry_duration(), 0.0);
+
+            let upload_result = backend.upload(upload_info()).unwrap_err();
+
+            assert_eq!(counter, 3);
+            assert!(matches!(upload_result, Error::RateLimited));
+        }
+
+        #[test]
+        fn upload_retries_server_errors_when_asked() {
+            let mut counter = 0;
+            let success = UploadResponse {
+                id: AssetId::Id(10),
+            };
+            let inner = CountUploads::new(&mut counter).with_results(vec![
+                Err(classify_api_error(server_error())),
+                Ok(success.clone()),
+            ]);
+            let mut backend =
+                RetryBackend::new(inner, 5, retry_duration(), 0.0).with_server_error_retries(true);
+
+            let upload_result = backend.upload(upload_info()).unwrap();
+
+            assert_eq!(counter, 2);
+            assert_eq!(upload_result, success);
+        }
+
+        #[test]
+        fn upload_does_not_retry_server_errors_by_default() {
+            let mut counter = 0;
+            let inner = CountUploads::new(&mut counter)
+                .with_results(vec![Err(classify_api_error(server_error()))]);
+            let mut backend = RetryBackend::new(inner, 5, retry_duration(), 0.0);
+
+            let upload_result = backend.upload(upload_info()).unwrap_err();
+
+            assert_eq!(counter, 1);
+            assert!(matches!(upload_result, Error::ServerError { .. }));
+        }
+
+        fn server_error() -> RobloxApiError {
+            RobloxApiError::ResponseError {
+                status: StatusCode::BAD_GATEWAY,
+                body: String::new(),
+            }
+        }
+
+        struct AlwaysRateLimited {
+            uploads: usize,
+        }
+
+        impl SyncBackend for AlwaysRateLimited {
+            fn upload(&mut self, _data: UploadInfo) -> Result<UploadResponse, Error> {
+                self.uploads += 1;
+                Err(Error::RateLimited)
+            }
+        }
+
+        #[test]
+        fn retry_budget_is_shared_between_uploads() {
+            let budget = Arc::new(AtomicUsize::new(3));
+            let mut backend =
+                RetryBackend::new(AlwaysRateLimited { uploads: 0 }, 5, Duration::ZERO, 0.0)
+                    .with_retry_budget(Arc::clone(&budget));
+
+            // The first upload uses up the whole budget, so the second one is
+            // only attempted once.
+            let first = backend.upload(upload_info()).unwrap_err();
+            assert!(matches!(first, Error::RateLimited));
+            assert_eq!(backend.inner.uploads, 4);
+
+            let second = backend.upload(upload_info()).unwrap_err();
+            assert!(matches!(second, Error::RateLimited));
+            assert_eq!(backend.inner.uploads, 5);
+            assert_eq!(budget.load(Ordering::SeqCst), 0);
+        }
+
+        #[test]
+        fn upload_retries_timeouts() {
+            let mut counter = 0;
+            let success = UploadResponse {
+                id: AssetId::Id(10),
+            };
+            let inner = CountUploads::new(&mut counter).with_results(vec![
+                Err(Error::Timeout),
+                Err(Error::RateLimited),
+                Ok(success.clone()),
+            ]);
+            let mut backend = RetryBackend::new(inner, 5, retry_duration(), 0.0);
 
-            let upload_result = backend.upload(any_upload_info()).unwrap_err();
+            let upload_result = backend.upload(upload_info()).unwrap();
 
             assert_eq!(counter, 3);
-            assert!(match upload_result {
-                Error::RateLimited => true,
-                _ => false,
+            assert_eq!(upload_result, success);
+        }
+    }
+
+    mod test_hook_backend {
+        use super::*;
+
+        #[test]
+        fn failing_hooks_are_only_kept_by_strict_backends() {
+            let mut lenient = HookBackend::new(AlwaysSucceeds, "exit 3".to_owned(), false);
+            assert!(lenient.upload(upload_info()).is_ok());
+            assert!(lenient.take_failures().is_empty());
+
+            // The upload went through, so its ID is still returned.
+            let mut strict = HookBackend::new(AlwaysSucceeds, "exit 3".to_owned(), true);
+            let response = strict.upload(upload_info()).unwrap();
+            assert_eq!(response.id, AssetId::Id(42));
+
+            let failures = strict.take_failures();
+            assert_eq!(failures.len(), 1);
+            assert!(matches!(failures[0], Error::Hook { .. }));
+            assert!(strict.take_failures().is_empty());
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn hooks_are_given_the_uploaded_asset() {
+            let mut backend = HookBackend::new(
+                AlwaysSucceeds,
+                r#"test "$TARMAC_ASSET_ID" = 42 && test "$TARMAC_ASSET_NAME" = foo"#.to_owned(),
+                true,
+            );
+            assert!(backend.upload(upload_info()).is_ok());
+        }
+    }
+
+    mod test_local_backend {
+        use super::*;
+
+        #[test]
+        fn names_differing_only_by_case_are_found() {
+            let mut backend = LocalSyncBackend::with_content_path(PathBuf::new(), None, false);
+
+            assert_eq!(backend.record_file_name("Icon.png"), None);
+            assert_eq!(backend.record_file_name("Icon.png"), None);
+            assert_eq!(backend.record_file_name("Other.png"), None);
+            assert_eq!(backend.record_file_name("icon.png"), Some("Icon.png"));
+        }
+
+        #[test]
+        fn unwritable_content_paths_are_found() {
+            let root = std::env::temp_dir()
+                .join(format!("tarmac-test-content-path-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+
+            let backend = LocalSyncBackend::with_content_path(root.clone(), None, false);
+            backend.check_writable().unwrap();
+            assert_eq!(fs::read_dir(root.join(".tarmac")).unwrap().count(), 0);
+
+            // A file where the content folder should be can't be written into.
+            let file_path = root.join("file");
+            fs::write(&file_path, b"").unwrap();
+            let backend = LocalSyncBackend::with_content_path(file_path, None, false);
+            assert!(matches!(
+                backend.check_writable(),
+                Err(Error::ContentPathNotWritable { .. })
+            ));
+
+            fs::remove_dir_all(&root).unwrap();
+        }
+    }
+
+    mod test_classify_api_error {
+        use super::*;
+
+        fn response_error(status: StatusCode) -> Error {
+            classify_api_error(RobloxApiError::ResponseError {
+                status,
+                body: String::new(),
+            })
+        }
+
+        #[test]
+        fn rate_limits_are_retryable() {
+            assert!(matches!(
+                response_error(StatusCode::TOO_MANY_REQUESTS),
+                Error::RateLimited
+            ));
+        }
+
+        #[test]
+        fn server_errors_are_kept_apart() {
+            let err = response_error(StatusCode::SERVICE_UNAVAILABLE);
+
+            assert!(matches!(err, Error::ServerError { .. }));
+            assert!(!err.is_retryable());
+        }
+
+        #[test]
+        fn client_errors_are_fatal() {
+            for status in &[StatusCode::BAD_REQUEST, StatusCode::FORBIDDEN] {
+                assert!(!response_error(*status).is_retryable());
+            }
+
+            let err = classify_api_error(RobloxApiError::ApiError {
+                message: "Asset is not appropriate".to_owned(),
             });
+            assert!(!err.is_retryable());
+        }
+
+        /// Stands in for the errors that wrap an I/O error inside of reqwest,
+        /// which can't be made without a real connection.
+        #[derive(Debug, thiserror::Error)]
+        #[error("error trying to connect")]
+        struct ConnectError {
+            source: io::Error,
+        }
+
+        #[test]
+        fn connection_errors_are_found_in_the_source_chain() {
+            let reset = ConnectError {
+                source: io::Error::new(io::ErrorKind::ConnectionReset, "connection reset"),
+            };
+            assert!(has_io_cause(&reset));
+
+            let not_io = RobloxApiError::ApiError {
+                message: "Asset is not appropriate".to_owned(),
+            };
+            assert!(!has_io_cause(&not_io));
+        }
+    }
+
+    mod test_throttle_backend {
+        use super::*;
+
+        use std::{cell::Cell, rc::Rc};
+
+        #[test]
+        fn uploads_are_spaced_out() {
+            let start = Instant::now();
+            let clock = Rc::new(Cell::new(start));
+            let slept = Rc::new(Cell::new(Duration::default()));
+
+            let now = Rc::clone(&clock);
+            let (sleep_clock, sleep_total) = (Rc::clone(&clock), Rc::clone(&slept));
+            let mut backend = ThrottleBackend::new(AlwaysSucceeds, 60).with_clock(
+                move || now.get(),
+                move |duration| {
+                    sleep_clock.set(sleep_clock.get() + duration);
+                    sleep_total.set(sleep_total.get() + duration);
+                },
+            );
+
+            // The first upload goes through right away, and the next two each
+            // wait out the rest of their second.
+            backend.upload(upload_info()).unwrap();
+            clock.set(clock.get() + Duration::from_millis(250));
+            backend.upload(upload_info()).unwrap();
+            backend.upload(upload_info()).unwrap();
+
+            assert_eq!(slept.get(), Duration::from_millis(1750));
+            assert_eq!(clock.get() - start, Duration::from_secs(2));
+        }
+
+        #[test]
+        fn slow_uploads_do_not_wait() {
+            let clock = Rc::new(Cell::new(Instant::now()));
+            let now = Rc::clone(&clock);
+            let mut backend = ThrottleBackend::new(AlwaysSucceeds, 60)
+                .with_clock(move || now.get(), |_| panic!("should not sleep"));
+
+            backend.upload(upload_info()).unwrap();
+            clock.set(clock.get() + Duration::from_secs(2));
+            backend.upload(upload_info()).unwrap();
         }
     }
 }